@@ -1,17 +1,21 @@
-use std::collections::BTreeMap;
 use std::io::Write;
 use std::{
     env,
-    fs::{self, OpenOptions},
+    fs::{self, File, OpenOptions},
     process,
+    str::FromStr,
 };
 
 use anyhow::Context;
 use itell::cms::{
-    get_embedding_slugs, perform_health_check, serialize_page, HealthCheckData, PageData,
-    VolumeData,
+    export_cartridge, export_epub, export_qti, generate_cloze_tests, get_embedding_slugs,
+    has_errors, perform_health_check, sanitize_pages, serialize_bundle, serialize_bundle_ndjson,
+    serialize_gift, serialize_page, serialize_toc, serialize_volume, validate_assignments,
+    validate_cloze_tests, validate_free_pages, validate_quizzes, validate_reveal_modes,
+    validate_slugs, ClozeOptions, ClozeStrategy, Diagnostic, HealthCheckData, Navigation,
+    NavigationRule, PageData, PageNavigation, Removal, SanitizeReport, Severity, VolumeConfig,
+    VolumeData, VolumeTree,
 };
-use serde::Serialize;
 
 const BOLD: &str = "\x1b[1m";
 const RESET: &str = "\x1b[0m";
@@ -25,6 +29,49 @@ pub struct Config {
     pub output_dir: String,
     pub embeddings_supabase_url: Option<String>,
     pub embeddings_supabase_api_key: Option<String>,
+    pub navigation_rule: NavigationRule,
+    pub format: OutputFormat,
+    /// generate cloze tests for pages without one
+    pub cloze: Option<ClozeOptions>,
+}
+
+/// How the volume is written to the output directory
+#[derive(Debug, Clone, Copy)]
+pub enum OutputFormat {
+    /// one markdown file with YAML frontmatter per page, plus `volume.yaml` and `toc.json`
+    Markdown,
+    /// the whole volume as a single `volume.json` bundle
+    Json,
+    /// `volume.ndjson` with one record per line for the volume, each page and each chunk
+    Ndjson,
+    /// an EPUB 3 file named after the volume slug
+    Epub,
+    /// an IMS Common Cartridge package named after the volume slug, for LMS import
+    Cartridge,
+    /// every quiz of the volume as a QTI 2.1 package named after the volume slug
+    Qti,
+    /// every quiz of the volume as a Moodle GIFT file named after the volume slug
+    Gift,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "epub" => Ok(OutputFormat::Epub),
+            "cartridge" => Ok(OutputFormat::Cartridge),
+            "qti" => Ok(OutputFormat::Qti),
+            "gift" => Ok(OutputFormat::Gift),
+            other => anyhow::bail!(
+                "unknown format '{}', expected markdown, json, ndjson, epub, cartridge, qti or gift",
+                other
+            ),
+        }
+    }
 }
 
 impl Config {
@@ -39,11 +86,18 @@ impl Config {
             output_dir: output_dir.to_string(),
             embeddings_supabase_url,
             embeddings_supabase_api_key,
+            navigation_rule: NavigationRule::IncludeAll,
+            format: OutputFormat::Markdown,
+            cloze: None,
         }
     }
 }
 
-fn parse_config(mut args: impl Iterator<Item = String>) -> anyhow::Result<Config> {
+fn parse_config(args: impl Iterator<Item = String>) -> anyhow::Result<Config> {
+    let (flags, positional): (Vec<String>, Vec<String>) =
+        args.partition(|arg| arg.starts_with("--"));
+    let mut args = positional.into_iter();
+
     let volume_id = args.next().context("volume_id is required, search for the 'documentId` field at https://itell-strapi-um5h.onrender.com/api/texts/")?;
     let output_dir = args.next().unwrap_or(DEFAULT_OUTPUT_DIR.to_string());
 
@@ -52,21 +106,80 @@ fn parse_config(mut args: impl Iterator<Item = String>) -> anyhow::Result<Config
     let embeddings_supabase_url = env::var("EMBEDDINGS_SUPABASE_URL").ok();
     let embeddings_supabase_api_key = env::var("EMBEDDINGS_SUPABASE_API_KEY").ok();
 
-    Ok(Config::new(
+    let mut config = Config::new(
         volume_id,
         &output_dir,
         embeddings_supabase_url,
         embeddings_supabase_api_key,
-    ))
+    );
+
+    let mut cloze_seed = 0;
+    for flag in flags {
+        match flag.split_once('=') {
+            Some(("--format", format)) => config.format = format.parse()?,
+            Some(("--generate-cloze", strategy)) => {
+                config.cloze = Some(ClozeOptions {
+                    strategy: parse_cloze_strategy(strategy)?,
+                    ..ClozeOptions::default()
+                })
+            }
+            Some(("--cloze-seed", seed)) => {
+                cloze_seed = seed
+                    .parse()
+                    .context(format!("invalid cloze seed '{}'", seed))?
+            }
+            None if flag == "--generate-cloze" => config.cloze = Some(ClozeOptions::default()),
+            None if flag == "--skip-empty-pages" => {
+                config.navigation_rule = NavigationRule::SkipEmpty
+            }
+            _ => anyhow::bail!("unknown option '{}'", flag),
+        }
+    }
+    if let Some(cloze) = &mut config.cloze {
+        cloze.seed = cloze_seed;
+    }
+
+    Ok(config)
+}
+
+/// `nth:N`, `content` or `frequency:PATH`, the file lists one common word per line
+fn parse_cloze_strategy(value: &str) -> anyhow::Result<ClozeStrategy> {
+    match value.split_once(':') {
+        Some(("nth", n)) => match n.parse() {
+            Ok(n) if n > 1 => Ok(ClozeStrategy::EveryNth(n)),
+            _ => anyhow::bail!("invalid cloze word interval '{}', expected a number above 1", n),
+        },
+        Some(("frequency", path)) => {
+            let words = fs::read_to_string(path)
+                .context(format!("failed to read word frequency list {}", path))?;
+            Ok(ClozeStrategy::Frequency(
+                words
+                    .lines()
+                    .map(|word| word.trim().to_lowercase())
+                    .filter(|word| !word.is_empty())
+                    .collect(),
+            ))
+        }
+        None if value == "content" => Ok(ClozeStrategy::ContentWords),
+        _ => anyhow::bail!(
+            "unknown cloze strategy '{}', expected nth:N, content or frequency:PATH",
+            value
+        ),
+    }
 }
 
 fn main() -> anyhow::Result<()> {
-    let args = env::args().skip(1);
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "--config-schema") {
+        println!("{}", serde_json::to_string_pretty(&VolumeConfig::schema())?);
+        return Ok(());
+    }
 
-    let config = match parse_config(args) {
+    let config = match parse_config(args.into_iter()) {
         Ok(config) => config,
         Err(e) => {
-            eprintln!("Usage: cargo run <volume_id> [output_dir]");
+            eprintln!("Usage: cargo run <volume_id> [output_dir] [--skip-empty-pages] [--format=markdown|json|ndjson|epub|cartridge|qti|gift] [--generate-cloze[=nth:N|content|frequency:PATH]] [--cloze-seed=N] | --config-schema");
             return Err(e);
         }
     };
@@ -76,27 +189,86 @@ fn main() -> anyhow::Result<()> {
             "failed to fetch volume data with id {}, make sure you provide the correct `documentId` found at https://itell-strapi-um5h.onrender.com/api/texts/",
             config.volume_id.as_str()
         ))?;
-    let pages = itell::cms::collect_pages(&volume).context("failed to collect pages")?;
+    let mut pages = itell::cms::collect_pages(&volume).context("failed to collect pages")?;
 
-    create_output_dir(&config.output_dir).context("failed to create output directory")?;
+    let sanitize_reports = sanitize_pages(&mut pages);
+    print_sanitize_summary(&sanitize_reports);
 
-    // Create volume metadata
-    let volume_str = create_volume_metadata(&volume, &config.output_dir)
-        .context("failed to create volume metadata")?;
+    if let Some(cloze) = &config.cloze {
+        let generated = generate_cloze_tests(&mut pages, cloze);
+        println!("Generated {} cloze tests", generated);
+    }
 
-    // Sort and create pages
-    let mut sorted_pages: Vec<&PageData> = pages.iter().collect();
-    sorted_pages.sort_by_key(|page| page.order);
+    let tree = VolumeTree::build(&pages);
+
+    let mut diagnostics = volume
+        .volume_config
+        .as_ref()
+        .map(VolumeConfig::validate)
+        .unwrap_or_default();
+    diagnostics.extend(validate_slugs(&pages));
+    diagnostics.extend(validate_free_pages(&volume.free_pages, &pages));
+    diagnostics.extend(tree.validate());
+    diagnostics.extend(validate_reveal_modes(&pages));
+    diagnostics.extend(validate_assignments(&pages));
+    diagnostics.extend(validate_quizzes(&pages));
+    diagnostics.extend(validate_cloze_tests(&pages));
+    print_diagnostics(&diagnostics);
+    if has_errors(&diagnostics) {
+        println!("{}❌ Content validation failed!{}", RED, RESET);
+        process::exit(1);
+    }
 
-    for (idx, page) in sorted_pages.iter().enumerate() {
-        let next_slug = if idx == sorted_pages.len() - 1 {
-            None
-        } else {
-            Some(sorted_pages[idx + 1].slug.as_str())
-        };
-        if let Err(e) = create_page(page, &config.output_dir, next_slug) {
-            eprintln!("{}Error writing page {}: {}{}", RED, page.slug, e, RESET);
-            process::exit(1);
+    create_output_dir(&config.output_dir).context("failed to create output directory")?;
+
+    let navigation = Navigation::build(&tree, config.navigation_rule);
+
+    match config.format {
+        OutputFormat::Markdown => {
+            // Create volume metadata
+            create_volume_metadata(&volume, &pages, &config.output_dir)
+                .context("failed to create volume metadata")?;
+
+            // Create pages with navigation computed from the chapter tree
+            let no_navigation = PageNavigation::default();
+            for node in tree.pages() {
+                let page = node.page;
+                let page_navigation = navigation.get(&page.slug).unwrap_or(&no_navigation);
+                if let Err(e) = create_page(page, &config.output_dir, page_navigation) {
+                    eprintln!("{}Error writing page {}: {}{}", RED, page.slug, e, RESET);
+                    process::exit(1);
+                }
+            }
+
+            create_toc(&pages, &config.output_dir)
+                .context("failed to create table of contents")?;
+        }
+        OutputFormat::Json => {
+            let content = serialize_bundle(&volume, &tree, &navigation)?;
+            create_file(&config.output_dir, "volume.json", &content)?;
+        }
+        OutputFormat::Ndjson => {
+            let content = serialize_bundle_ndjson(&volume, &tree, &navigation)?;
+            create_file(&config.output_dir, "volume.ndjson", &content)?;
+        }
+        OutputFormat::Epub => {
+            let path = format!("{}/{}.epub", config.output_dir, volume.slug);
+            let file = File::create(&path).context(format!("failed to create {}", path))?;
+            export_epub(&volume, &pages, file).context("failed to export EPUB")?;
+        }
+        OutputFormat::Cartridge => {
+            let path = format!("{}/{}.imscc", config.output_dir, volume.slug);
+            let file = File::create(&path).context(format!("failed to create {}", path))?;
+            export_cartridge(&volume, &pages, file).context("failed to export cartridge")?;
+        }
+        OutputFormat::Qti => {
+            let path = format!("{}/{}.qti.zip", config.output_dir, volume.slug);
+            let file = File::create(&path).context(format!("failed to create {}", path))?;
+            export_qti(&volume, &pages, file).context("failed to export QTI package")?;
+        }
+        OutputFormat::Gift => {
+            let content = serialize_gift(&volume, &pages);
+            create_file(&config.output_dir, &format!("{}.gift", volume.slug), &content)?;
         }
     }
 
@@ -111,7 +283,7 @@ fn main() -> anyhow::Result<()> {
             let embedding_slugs_array = get_embedding_slugs(
                 url,
                 api_key,
-                &volume.slug.as_str(),
+                volume.slug.as_str(),
             )
             .context("Failed to get embedding slugs")?;
 
@@ -144,58 +316,65 @@ fn main() -> anyhow::Result<()> {
     }
 }
 
-fn create_volume_metadata(volume: &VolumeData, output_dir: &str) -> anyhow::Result<String> {
+fn create_volume_metadata(
+    volume: &VolumeData,
+    pages: &[PageData],
+    output_dir: &str,
+) -> anyhow::Result<String> {
     let mut file = OpenOptions::new()
         .create_new(true)
         .write(true)
         .open(format!("{}/volume.yaml", output_dir))
         .context("failed to open file for volume.toml")?;
 
-    let mut map = BTreeMap::<&str, VolumeFrontmatter>::new();
-    map.insert("title", VolumeFrontmatter::Title(volume.title.as_str()));
-    map.insert("slug", VolumeFrontmatter::Slug(volume.slug.as_str()));
-    map.insert(
-        "description",
-        VolumeFrontmatter::Description(volume.description.as_str()),
-    );
-    map.insert(
-        "free_pages",
-        VolumeFrontmatter::FreePages(volume.free_pages.as_slice()),
-    );
-
-    map.insert(
-        "summary",
-        VolumeFrontmatter::Summary(volume.summary.as_deref()),
-    );
-
-    // Flatten VolumeConfig fields into top-level fields
-    if let Some(volume_config) = &volume.volume_config {
-        if let Some(config_object) = volume_config.as_object() {
-            for (key, value) in config_object {
-                map.insert(Box::leak(key.clone().into_boxed_str()), VolumeFrontmatter::VolumeConfigValue(value));
-            }
-        }
-    }
-
-    let content = serde_yaml_ng::to_string(&map).context("failed to serialize volume metadata")?;
+    let content = serialize_volume(volume, pages).context("failed to serialize volume metadata")?;
     write!(file, "{}", content).context("failed to write volume metadata")?;
 
     Ok(content)
 }
 
-fn create_page(page: &PageData, output_dir: &str, next_slug: Option<&str>) -> anyhow::Result<()> {
+fn create_page(
+    page: &PageData,
+    output_dir: &str,
+    navigation: &PageNavigation,
+) -> anyhow::Result<()> {
     let mut file = OpenOptions::new()
         .create_new(true)
         .write(true)
         .open(format!("{}/{}.md", output_dir, page.slug))
         .context(format!("failed to open file for {}", page.slug))?;
 
-    let content = serialize_page(page, next_slug).context("failed to serialize page")?;
+    let content = serialize_page(page, navigation).context("failed to serialize page")?;
     write!(file, "{}", content).context("failed to write page")?;
 
     Ok(())
 }
 
+fn create_toc(pages: &[PageData], output_dir: &str) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new()
+        .create_new(true)
+        .write(true)
+        .open(format!("{}/toc.json", output_dir))
+        .context("failed to open file for toc.json")?;
+
+    let content = serialize_toc(pages)?;
+    write!(file, "{}", content).context("failed to write toc")?;
+
+    Ok(())
+}
+
+fn create_file(output_dir: &str, name: &str, content: &str) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new()
+        .create_new(true)
+        .write(true)
+        .open(format!("{}/{}", output_dir, name))
+        .context(format!("failed to open file for {}", name))?;
+
+    write!(file, "{}", content).context(format!("failed to write {}", name))?;
+
+    Ok(())
+}
+
 fn create_output_dir(output_dir: &str) -> anyhow::Result<()> {
     if fs::metadata(output_dir).is_ok() {
         fs::remove_dir_all(output_dir)?;
@@ -205,6 +384,60 @@ fn create_output_dir(output_dir: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn print_sanitize_summary(reports: &[SanitizeReport]) {
+    if reports.is_empty() {
+        return;
+    }
+
+    println!(
+        "{}⚠️  Removed unsafe HTML from {} chunks:{}",
+        YELLOW,
+        reports.len(),
+        RESET
+    );
+    for report in reports {
+        println!("  Page '{}', chunk '{}'", report.page_slug, report.chunk_slug);
+        for removal in &report.removed {
+            let description = match removal {
+                Removal::Element { tag } => format!("<{}> element", tag),
+                Removal::EventHandler { tag, attribute } => {
+                    format!("'{}' handler on <{}>", attribute, tag)
+                }
+                Removal::Style { tag } => format!("'style' attribute on <{}>", tag),
+                Removal::JavascriptUrl { url } => format!("URL '{}'", url),
+                Removal::Tag { tag } => format!("<{}> tag", tag),
+            };
+            println!("    - {}", description);
+        }
+    }
+    println!();
+}
+
+fn print_diagnostics(diagnostics: &[Diagnostic]) {
+    if diagnostics.is_empty() {
+        return;
+    }
+
+    println!("{BOLD}CONTENT VALIDATION:{RESET}");
+    for diagnostic in diagnostics {
+        let (color, label) = match diagnostic.severity {
+            Severity::Error => (RED, "error"),
+            Severity::Warning => (YELLOW, "warning"),
+        };
+        let location = match (&diagnostic.page_slug, &diagnostic.chunk_slug) {
+            (Some(page), Some(chunk)) => format!(" [{} > {}]", page, chunk),
+            (Some(page), None) => format!(" [{}]", page),
+            (None, Some(chunk)) => format!(" [{}]", chunk),
+            (None, None) => String::new(),
+        };
+        println!(
+            "  {}{}{}{}: {}",
+            color, label, RESET, location, diagnostic.message
+        );
+    }
+    println!();
+}
+
 fn print_health_check_summary(health_check: &HealthCheckData) -> bool {
     println!("--------------------");
     println!("\n{BOLD}HEALTH CHECK SUMMARY:{RESET}");
@@ -247,14 +480,3 @@ fn print_health_check_summary(health_check: &HealthCheckData) -> bool {
     println!();
     validation_passed
 }
-
-#[derive(Serialize, Debug)]
-#[serde(untagged)]
-enum VolumeFrontmatter<'a> {
-    Title(&'a str),
-    Slug(&'a str),
-    Description(&'a str),
-    FreePages(&'a [String]),
-    Summary(Option<&'a str>),
-    VolumeConfigValue(&'a serde_json::Value),
-}
@@ -0,0 +1,26 @@
+use std::{env, fs};
+
+use anyhow::Context;
+use itell::cms::parse_gift;
+
+/// Converts a Moodle GIFT file into quiz YAML that can be pasted into a page's generated questions
+fn main() -> anyhow::Result<()> {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("Usage: cargo run --bin import_gift <file.gift>");
+        anyhow::bail!("a GIFT file is required");
+    };
+
+    let text = fs::read_to_string(&path).context(format!("failed to read {}", path))?;
+    let categories = parse_gift(&text).context(format!("failed to parse {}", path))?;
+
+    for category in categories {
+        if !category.path.is_empty() {
+            println!("# {}", category.path.join(" / "));
+        }
+        let yaml =
+            serde_yaml_ng::to_string(&category.items).context("failed to serialize quiz items")?;
+        println!("{}", yaml);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use std::{env, fs};
+
+use anyhow::Context;
+use itell::cms::{score_response, CriThresholds};
+
+const USAGE: &str = "Usage: cargo run --bin score_cri <responses.csv> [--rouge-l=0.3] [--keyword-coverage=0.5] [--edit-similarity=0]";
+
+/// Scores a CSV of learner responses against reference answers and prints it back with
+/// the scores appended. The CSV needs a header with `answer` (or `reference`) and `response`
+/// columns, and may have `keywords` separated by `;`. Other columns are kept as they are.
+fn main() -> anyhow::Result<()> {
+    let (flags, positional): (Vec<String>, Vec<String>) =
+        env::args().skip(1).partition(|arg| arg.starts_with("--"));
+
+    let Some(path) = positional.first() else {
+        eprintln!("{}", USAGE);
+        anyhow::bail!("a CSV file is required");
+    };
+
+    let mut thresholds = CriThresholds::default();
+    for flag in &flags {
+        let (name, value) = flag.split_once('=').unwrap_or((flag, ""));
+        let threshold = match name {
+            "--rouge-l" => &mut thresholds.rouge_l,
+            "--keyword-coverage" => &mut thresholds.keyword_coverage,
+            "--edit-similarity" => &mut thresholds.edit_similarity,
+            _ => {
+                eprintln!("{}", USAGE);
+                anyhow::bail!("unknown option '{}'", flag);
+            }
+        };
+        *threshold = value
+            .parse()
+            .context(format!("invalid threshold '{}' for {}", value, name))?;
+    }
+
+    let text = fs::read_to_string(path).context(format!("failed to read {}", path))?;
+    // spreadsheet apps such as Excel start UTF-8 CSVs with a byte order mark
+    let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+    let mut rows = parse_csv(text).context(format!("failed to parse {}", path))?;
+    if rows.is_empty() {
+        anyhow::bail!("{} is empty", path);
+    }
+
+    let header = rows.remove(0);
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|h| names.contains(&h.trim().to_lowercase().as_str()))
+    };
+    let answer_column = column(&["answer", "reference"])
+        .context("the CSV header needs an 'answer' or 'reference' column")?;
+    let response_column =
+        column(&["response"]).context("the CSV header needs a 'response' column")?;
+    let keywords_column = column(&["keywords"]);
+
+    // extra cells have no column to go in, so they would be lost in the output
+    let wide: Vec<String> = rows
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| row.len() > header.len())
+        .map(|(index, row)| format!("row {} has {} columns", index + 2, row.len()))
+        .collect();
+    if !wide.is_empty() {
+        anyhow::bail!(
+            "the header has {} columns, but {}",
+            header.len(),
+            wide.join(", ")
+        );
+    }
+
+    let mut output = header.clone();
+    output.extend(["rouge_l", "keyword_coverage", "edit_similarity", "passed"].map(String::from));
+    println!("{}", format_row(&output));
+
+    let total = rows.len();
+    let mut passed = 0;
+    for (index, mut row) in rows.into_iter().enumerate() {
+        let cell = |column: usize| row.get(column).map(String::as_str).unwrap_or_default();
+        if row.len() < header.len() {
+            eprintln!(
+                "row {} has {} of {} columns",
+                index + 2,
+                row.len(),
+                header.len()
+            );
+        }
+
+        let keywords: Vec<String> = keywords_column
+            .map(|column| cell(column).split(';').map(str::to_string).collect())
+            .unwrap_or_default();
+        let score = score_response(
+            cell(answer_column),
+            &keywords,
+            cell(response_column),
+            &thresholds,
+        );
+        passed += usize::from(score.passed);
+
+        row.resize(header.len(), String::new());
+        row.extend([
+            format!("{:.3}", score.rouge_l),
+            score
+                .keyword_coverage
+                .map(|coverage| format!("{:.3}", coverage))
+                .unwrap_or_default(),
+            format!("{:.3}", score.edit_similarity),
+            score.passed.to_string(),
+        ]);
+        println!("{}", format_row(&row));
+    }
+
+    eprintln!(
+        "{} of {} responses passed (ROUGE-L >= {}, keyword coverage >= {}, edit similarity >= {})",
+        passed, total, thresholds.rouge_l, thresholds.keyword_coverage, thresholds.edit_similarity
+    );
+
+    Ok(())
+}
+
+/// Splits RFC 4180 CSV into rows, quoted fields may contain commas, newlines and `""`
+fn parse_csv(text: &str) -> anyhow::Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if quoted {
+        anyhow::bail!("unterminated quoted field in row {}", rows.len() + 1);
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    // blank lines carry no response
+    rows.retain(|row| !(row.len() == 1 && row[0].trim().is_empty()));
+    Ok(rows)
+}
+
+fn format_row(row: &[String]) -> String {
+    row.iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
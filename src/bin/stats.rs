@@ -0,0 +1,95 @@
+use std::env;
+
+use anyhow::Context;
+use itell::cms::{build_stats, collect_pages, get_volume_data, Stats, StatsItem, WORDS_PER_MINUTE};
+
+const USAGE: &str = "Usage: cargo run --bin stats <volume_id> [--json]";
+/// Widest title column, longer titles are cut
+const MAX_TITLE_WIDTH: usize = 48;
+
+/// Prints word counts, reading times and assessment counts of a volume by chapter and page
+fn main() -> anyhow::Result<()> {
+    let (flags, positional): (Vec<String>, Vec<String>) =
+        env::args().skip(1).partition(|arg| arg.starts_with("--"));
+
+    let Some(volume_id) = positional.first() else {
+        eprintln!("{}", USAGE);
+        anyhow::bail!("volume_id is required");
+    };
+    let mut json = false;
+    for flag in &flags {
+        match flag.as_str() {
+            "--json" => json = true,
+            _ => {
+                eprintln!("{}", USAGE);
+                anyhow::bail!("unknown option '{}'", flag);
+            }
+        }
+    }
+
+    let volume = get_volume_data(volume_id)
+        .context(format!("failed to fetch volume data with id {}", volume_id))?;
+    let pages = collect_pages(&volume).context("failed to collect pages")?;
+    let stats = build_stats(&pages);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    let mut rows: Vec<(String, Stats)> = Vec::new();
+    for item in &stats.items {
+        match item {
+            StatsItem::Chapter {
+                title,
+                stats,
+                pages,
+                ..
+            } => {
+                rows.push((title.to_string(), *stats));
+                rows.extend(pages.iter().map(|p| (format!("  {}", p.title), p.stats)));
+            }
+            StatsItem::Page(page) => rows.push((page.title.to_string(), page.stats)),
+        }
+    }
+    rows.push((volume.title.clone(), stats.stats));
+
+    let width = rows
+        .iter()
+        .map(|(title, _)| title.chars().count())
+        .max()
+        .unwrap_or_default()
+        .clamp(5, MAX_TITLE_WIDTH);
+
+    println!(
+        "{:<width$}  {:>7}  {:>7}  {:>6}  {:>4}  {:>4}  {:>6}",
+        "Title", "Words", "Minutes", "Chunks", "CRI", "Quiz", "Videos"
+    );
+    let last = rows.len() - 1;
+    for (index, (title, stats)) in rows.iter().enumerate() {
+        if index == last {
+            println!("{}", "-".repeat(width + 46));
+        }
+        println!(
+            "{:<width$}  {:>7}  {:>7}  {:>6}  {:>4}  {:>4}  {:>6}",
+            truncate(title, width),
+            stats.words,
+            stats.reading_time,
+            stats.chunks,
+            stats.cri,
+            stats.quiz,
+            stats.videos
+        );
+    }
+    println!("\nReading time assumes {} words per minute.", WORDS_PER_MINUTE);
+
+    Ok(())
+}
+
+fn truncate(title: &str, width: usize) -> String {
+    if title.chars().count() <= width {
+        return title.to_string();
+    }
+    let cut: String = title.chars().take(width - 1).collect();
+    format!("{}…", cut)
+}
@@ -1,12 +1,62 @@
+mod access;
+mod assignment;
+mod bundle;
+mod cartridge;
 mod chunk;
+mod cloze;
+mod config;
+mod epub;
 mod fetch;
+mod gift;
 mod frontmatter;
 mod page;
+mod qti;
+mod quiz;
 mod healthcheck;
+mod html;
+mod markdown;
+mod navigation;
+mod sanitize;
+mod scoring;
+mod stats;
+mod text;
+mod toc;
+mod tree;
+mod validate;
 
-use chunk::{ChunkData, ChunkType, CriItem};
+use chunk::{ChunkData, ChunkType, CriItem, Exercise, RevealMode};
 use page::PageParent;
 
-pub use fetch::{collect_pages, get_volume_data, serialize_page, VolumeData};
+pub use access::resolve_free_pages;
+pub use assignment::{
+    validate_assignments, Assignment, AssignmentSettings, ClozeConfig, QuizConfig, SummaryConfig,
+};
+pub use bundle::{
+    build_bundle, serialize_bundle, serialize_bundle_ndjson, Bundle, BundleChunk, BundlePage,
+    BundleVolume, BUNDLE_SCHEMA_VERSION,
+};
+pub use config::{VolumeConfig, RESERVED_KEYS};
+pub use cartridge::export_cartridge;
+pub use cloze::{
+    generate_cloze, generate_cloze_tests, render_cloze_html, render_cloze_markdown, score_cloze,
+    validate_cloze_tests, ClozeOptions, ClozeScore, ClozeStrategy, ClozeTolerance, GapResult,
+};
+pub use epub::export_epub;
+pub use fetch::{collect_pages, get_volume_data, serialize_page, serialize_volume, VolumeData};
+pub use gift::{parse_gift, serialize_gift, GiftCategory};
 pub use healthcheck::{HealthCheckData, PageHealthCheck, perform_health_check, get_embedding_slugs, save_health_check_to_supabase};
-pub use page::PageData;
+pub use navigation::{ChapterPosition, Navigation, NavigationRule, PageNavigation, PagePosition};
+pub use page::{Difficulty, PageData, QuestionType, QuizAnswerItem, QuizItem};
+pub use qti::{export_qti, quiz_to_qti};
+pub use quiz::validate_quizzes;
+pub use sanitize::{sanitize_markdown, sanitize_pages, Removal, SanitizeReport};
+pub use scoring::{score_response, CriScore, CriThresholds};
+pub use stats::{
+    build_stats, chunk_stats, page_stats, reading_time, ChunkStats, PageStats, Stats, StatsItem,
+    VolumeStats, WORDS_PER_MINUTE,
+};
+pub use toc::{build_toc, serialize_toc, Toc, TocChunk, TocItem, TocPage};
+pub use tree::{ChapterNode, PageNode, TreeNode, VolumeTree};
+pub use validate::{
+    has_errors, validate_free_pages, validate_reveal_modes, validate_slugs, Diagnostic, Severity,
+};
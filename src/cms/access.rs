@@ -0,0 +1,69 @@
+use super::PageData;
+
+/// Separates the first and last page slug of a free page range
+const RANGE_SEPARATOR: &str = "..";
+
+/// Marks pages as free from the volume's `FreePages` entries.
+///
+/// An entry is a page slug, a chapter slug (all pages of the chapter), or an inclusive
+/// range of page slugs in reading order written as `first-page..last-page`.
+/// Unknown entries are skipped, they are reported by `validate_free_pages`.
+pub fn resolve_free_pages(entries: &[String], pages: &mut [PageData]) {
+    let (free, _) = free_page_slugs(entries, pages);
+
+    for page in pages.iter_mut() {
+        page.free = free.contains(&page.slug);
+    }
+}
+
+/// Entries that don't resolve to any page, with the reason
+pub(crate) fn invalid_free_pages(
+    entries: &[String],
+    pages: &[PageData],
+) -> Vec<(String, &'static str)> {
+    free_page_slugs(entries, pages).1
+}
+
+/// Slugs of the free pages, and the entries that don't resolve
+fn free_page_slugs(
+    entries: &[String],
+    pages: &[PageData],
+) -> (Vec<String>, Vec<(String, &'static str)>) {
+    let mut sorted: Vec<(usize, String)> = pages
+        .iter()
+        .map(|page| (page.order, page.slug.clone()))
+        .collect();
+    sorted.sort();
+    let position = |slug: &str| sorted.iter().position(|(_, s)| s == slug);
+
+    let mut free = Vec::<String>::new();
+    let mut invalid = Vec::<(String, &'static str)>::new();
+
+    for entry in entries.iter().map(|e| e.trim()).filter(|e| !e.is_empty()) {
+        if let Some((first, last)) = entry.split_once(RANGE_SEPARATOR) {
+            match (position(first.trim()), position(last.trim())) {
+                (Some(start), Some(end)) if start <= end => {
+                    free.extend(sorted[start..=end].iter().map(|(_, s)| s.clone()));
+                }
+                (Some(_), Some(_)) => {
+                    invalid.push((entry.to_string(), "is a range that ends before it starts"))
+                }
+                _ => invalid.push((entry.to_string(), "is a range with an unknown page")),
+            }
+        } else if position(entry).is_some() {
+            free.push(entry.to_string());
+        } else {
+            let chapter_pages: Vec<String> = pages
+                .iter()
+                .filter(|p| p.parent.as_ref().is_some_and(|c| c.slug == entry))
+                .map(|p| p.slug.clone())
+                .collect();
+            if chapter_pages.is_empty() {
+                invalid.push((entry.to_string(), "matches no page or chapter slug"));
+            }
+            free.extend(chapter_pages);
+        }
+    }
+
+    (free, invalid)
+}
@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+
+use super::{page::PageData, validate::Diagnostic};
+
+/// An evaluation a page asks of the reader, with its settings
+#[derive(Debug, Clone, PartialEq)]
+pub enum Assignment {
+    Summary(SummaryConfig),
+    Quiz(QuizConfig),
+    Cloze(ClozeConfig),
+    Exercises,
+}
+
+impl Assignment {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Assignment::Summary(_) => "summary",
+            Assignment::Quiz(_) => "quiz",
+            Assignment::Cloze(_) => "cloze",
+            Assignment::Exercises => "exercises",
+        }
+    }
+
+    /// Settings as JSON, `None` when nothing is set
+    fn config(&self) -> Option<Value> {
+        let config = match self {
+            Assignment::Summary(config) => serde_json::to_value(config),
+            Assignment::Quiz(config) => serde_json::to_value(config),
+            Assignment::Cloze(config) => serde_json::to_value(config),
+            Assignment::Exercises => return None,
+        };
+        config
+            .ok()
+            .filter(|value| value.as_object().is_some_and(|object| !object.is_empty()))
+    }
+}
+
+/// Serialized as its name, so `assignments` stays a list of strings
+impl Serialize for Assignment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SummaryConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_words: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_words: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuizConfig {
+    /// share of correct answers needed to pass, from 0 to 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pass_threshold: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClozeConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+}
+
+/// Settings of every assignment type, set in the volume config and overridden per page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssignmentSettings {
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub summary: SummaryConfig,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub quiz: QuizConfig,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub cloze: ClozeConfig,
+}
+
+impl AssignmentSettings {
+    /// Parses and checks the `AssignmentConfig` JSON of a page or the `assignments` volume config key
+    pub fn parse(value: &Value) -> Result<Self> {
+        let settings: AssignmentSettings = serde_json::from_value(value.clone())?;
+        settings.check()?;
+        Ok(settings)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Checks that the settings can be met
+    pub fn check(&self) -> Result<()> {
+        if let (Some(min), Some(max)) = (self.summary.min_words, self.summary.max_words) {
+            if min > max {
+                bail!("summary min_words {} is above max_words {}", min, max);
+            }
+        }
+        if let Some(threshold) = self.quiz.pass_threshold {
+            if !(0.0..=1.0).contains(&threshold) {
+                bail!("quiz pass_threshold {} must be between 0 and 1", threshold);
+            }
+        }
+        if self.cloze.max_attempts == Some(0) {
+            bail!("cloze max_attempts must be at least 1");
+        }
+        Ok(())
+    }
+
+    /// These settings with unset values taken from `defaults`
+    pub fn or(&self, defaults: &AssignmentSettings) -> AssignmentSettings {
+        AssignmentSettings {
+            summary: SummaryConfig {
+                min_words: self.summary.min_words.or(defaults.summary.min_words),
+                max_words: self.summary.max_words.or(defaults.summary.max_words),
+            },
+            quiz: QuizConfig {
+                pass_threshold: self.quiz.pass_threshold.or(defaults.quiz.pass_threshold),
+            },
+            cloze: ClozeConfig {
+                max_attempts: self.cloze.max_attempts.or(defaults.cloze.max_attempts),
+            },
+        }
+    }
+}
+
+/// Settings of the assignments that have any, keyed by assignment name
+pub fn assignment_config(assignments: &[Assignment]) -> BTreeMap<&'static str, Value> {
+    assignments
+        .iter()
+        .filter_map(|assignment| Some((assignment.name(), assignment.config()?)))
+        .collect()
+}
+
+/// Checks that every assignment of a page has the data it needs, and that no assignment is
+/// listed twice. Quiz, cloze or exercise data without its assignment is a warning.
+pub fn validate_assignments(pages: &[PageData]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for page in pages {
+        let has_quiz = page.quiz.as_ref().is_some_and(|quiz| !quiz.is_empty());
+        let has_cloze = page.cloze_test.is_some();
+        let has_exercises = page.chunks.iter().any(|chunk| chunk.exercise.is_some());
+        let listed = |name: &str| {
+            page.assignments
+                .iter()
+                .filter(|assignment| assignment.name() == name)
+                .count()
+        };
+
+        for (name, data, has_data) in [
+            ("summary", "content", true),
+            ("quiz", "quiz", has_quiz),
+            ("cloze", "cloze test", has_cloze),
+            ("exercises", "exercise chunk", has_exercises),
+        ] {
+            let count = listed(name);
+            if count > 1 {
+                diagnostics.push(Diagnostic::error(
+                    Some(&page.slug),
+                    None,
+                    format!("Assignment '{}' is listed {} times", name, count),
+                ));
+            }
+            if count > 0 && !has_data {
+                diagnostics.push(Diagnostic::error(
+                    Some(&page.slug),
+                    None,
+                    format!(
+                        "Assignment '{}' is listed, but the page has no {}",
+                        name, data
+                    ),
+                ));
+            }
+            if count == 0 && has_data && name != "summary" {
+                diagnostics.push(Diagnostic::warning(
+                    Some(&page.slug),
+                    None,
+                    format!(
+                        "Page has a {}, but no '{}' assignment asks for it",
+                        data, name
+                    ),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
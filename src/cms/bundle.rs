@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use github_slugger::Slugger;
+use serde::Serialize;
+
+use super::{
+    assignment::{assignment_config, Assignment},
+    chunk::{ChunkData, ChunkType, CriItem, Exercise, RevealMode},
+    config::VolumeConfig,
+    fetch::VolumeData,
+    frontmatter::Heading,
+    markdown::{page_slugger, transform_headings},
+    navigation::{Navigation, PageNavigation},
+    page::{ClozeTest, PageData, PageParent, QuizItem},
+    tree::VolumeTree,
+};
+
+/// Bumped whenever the bundle layout changes in a way consumers must handle
+pub const BUNDLE_SCHEMA_VERSION: u32 = 2;
+
+/// The whole volume as a single JSON document
+#[derive(Debug, Serialize)]
+pub struct Bundle<'a> {
+    pub schema_version: u32,
+    pub volume: BundleVolume<'a>,
+    pub pages: Vec<BundlePage<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BundleVolume<'a> {
+    pub title: &'a str,
+    pub slug: &'a str,
+    pub description: &'a str,
+    pub summary: Option<&'a str>,
+    pub free_pages: Vec<&'a str>,
+    pub config: Option<&'a VolumeConfig>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BundlePage<'a> {
+    pub title: &'a str,
+    pub slug: &'a str,
+    pub order: usize,
+    pub parent: Option<&'a PageParent>,
+    pub description: Option<&'a str>,
+    pub keywords: &'a [String],
+    pub cover_image: Option<&'a str>,
+    pub free: bool,
+    pub assignments: &'a [Assignment],
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub assignment_config: BTreeMap<&'static str, serde_json::Value>,
+    #[serde(flatten)]
+    pub navigation: Option<&'a PageNavigation<'a>>,
+    pub quiz: Option<&'a Vec<QuizItem>>,
+    pub cloze_test: Option<&'a ClozeTest>,
+    /// omitted when empty, NDJSON writes chunks as separate records
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<BundleChunk<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BundleChunk<'a> {
+    pub title: &'a str,
+    pub slug: &'a str,
+    #[serde(rename = "type")]
+    pub chunk_type: &'a ChunkType,
+    pub depth: usize,
+    pub show_header: bool,
+    pub reveal: RevealMode,
+    /// markdown with heading ids added
+    pub content: String,
+    pub headings: Vec<Heading>,
+    /// version 1 had at most one item, as an object or null
+    pub cri: &'a [CriItem],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exercise: Option<&'a Exercise>,
+}
+
+/// A line of the NDJSON output, the volume first, then each page followed by its chunks.
+/// Tagged with `record` since chunks already have a `type`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "record", rename_all = "lowercase")]
+enum Record<'a> {
+    Volume {
+        schema_version: u32,
+        #[serde(flatten)]
+        volume: BundleVolume<'a>,
+    },
+    Page(BundlePage<'a>),
+    Chunk {
+        page_slug: &'a str,
+        #[serde(flatten)]
+        chunk: BundleChunk<'a>,
+    },
+}
+
+/// Builds the bundle with pages in reading order
+pub fn build_bundle<'a>(
+    volume: &'a VolumeData,
+    tree: &VolumeTree<'a>,
+    navigation: &'a Navigation<'a>,
+) -> Bundle<'a> {
+    let pages: Vec<BundlePage> = tree
+        .pages()
+        .map(|node| bundle_page(node.page, navigation))
+        .collect();
+
+    let free_pages = pages
+        .iter()
+        .filter(|page| page.free)
+        .map(|page| page.slug)
+        .collect();
+
+    Bundle {
+        schema_version: BUNDLE_SCHEMA_VERSION,
+        volume: BundleVolume {
+            title: volume.title.as_str(),
+            slug: volume.slug.as_str(),
+            description: volume.description.as_str(),
+            summary: volume.summary.as_deref(),
+            free_pages,
+            config: volume.volume_config.as_ref(),
+        },
+        pages,
+    }
+}
+
+/// Serializes the volume to a single pretty-printed JSON document
+pub fn serialize_bundle<'a>(
+    volume: &'a VolumeData,
+    tree: &VolumeTree<'a>,
+    navigation: &'a Navigation<'a>,
+) -> Result<String> {
+    serde_json::to_string_pretty(&build_bundle(volume, tree, navigation))
+        .context("failed to serialize bundle")
+}
+
+/// Serializes the volume to NDJSON, one record per line
+pub fn serialize_bundle_ndjson<'a>(
+    volume: &'a VolumeData,
+    tree: &VolumeTree<'a>,
+    navigation: &'a Navigation<'a>,
+) -> Result<String> {
+    let bundle = build_bundle(volume, tree, navigation);
+    let mut records = vec![Record::Volume {
+        schema_version: bundle.schema_version,
+        volume: bundle.volume,
+    }];
+
+    for mut page in bundle.pages {
+        let chunks = std::mem::take(&mut page.chunks);
+        let page_slug = page.slug;
+        records.push(Record::Page(page));
+        records.extend(
+            chunks
+                .into_iter()
+                .map(|chunk| Record::Chunk { page_slug, chunk }),
+        );
+    }
+
+    let mut output = String::new();
+    for record in records {
+        output.push_str(&serde_json::to_string(&record).context("failed to serialize record")?);
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+fn bundle_page<'a>(page: &'a PageData, navigation: &'a Navigation<'a>) -> BundlePage<'a> {
+    let mut slugger = page_slugger(page);
+
+    BundlePage {
+        title: page.title.as_str(),
+        slug: page.slug.as_str(),
+        order: page.order,
+        parent: page.parent.as_ref(),
+        description: page.description.as_deref(),
+        keywords: &page.keywords,
+        cover_image: page.cover_image.as_deref(),
+        free: page.free,
+        assignments: &page.assignments,
+        assignment_config: assignment_config(&page.assignments),
+        navigation: navigation.get(&page.slug),
+        quiz: page.quiz.as_ref(),
+        cloze_test: page.cloze_test.as_ref(),
+        chunks: page
+            .chunks
+            .iter()
+            .map(|chunk| bundle_chunk(chunk, &mut slugger))
+            .collect(),
+    }
+}
+
+fn bundle_chunk<'a>(chunk: &'a ChunkData, slugger: &mut Slugger) -> BundleChunk<'a> {
+    let (content, headings) = transform_headings(&chunk.content, slugger);
+
+    BundleChunk {
+        title: chunk.title.as_str(),
+        slug: chunk.slug.as_str(),
+        chunk_type: &chunk.chunk_type,
+        depth: chunk.depth,
+        show_header: chunk.show_header,
+        reveal: chunk.reveal,
+        content,
+        headings,
+        cri: &chunk.cri,
+        exercise: chunk.exercise.as_ref(),
+    }
+}
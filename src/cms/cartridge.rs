@@ -0,0 +1,177 @@
+use std::io::{Seek, Write};
+
+use anyhow::{Context, Result};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use super::{
+    fetch::VolumeData,
+    html::{escape, render_chunks, volume_language, xhtml_document, STYLESHEET},
+    page::PageData,
+    qti::{identifier, quiz_to_qti},
+    tree::{PageNode, TreeNode, VolumeTree},
+};
+
+const QTI_RESOURCE_TYPE: &str = "imsqti_xmlv1p2/imscc_xmlv1p1/assessment";
+
+/// A page of the cartridge and its optional quiz
+struct Entry<'a> {
+    page: &'a PageData,
+    /// resource identifier, also the file stem
+    ident: String,
+    quiz: Option<String>,
+}
+
+/// Writes the volume as an IMS Common Cartridge 1.1 package (`.imscc`).
+///
+/// Pages become web content organized by chapter, and page quizzes become QTI 1.2
+/// assessments placed right after their page so the LMS can grade them.
+pub fn export_cartridge<W: Write + Seek>(
+    volume: &VolumeData,
+    pages: &[PageData],
+    writer: W,
+) -> Result<()> {
+    let tree = VolumeTree::build(pages);
+    let entries: Vec<Entry> = tree
+        .pages()
+        .enumerate()
+        .map(|(index, node)| Entry {
+            page: node.page,
+            ident: format!("page-{:03}", index + 1),
+            quiz: node
+                .page
+                .quiz
+                .as_ref()
+                .filter(|quiz| !quiz.is_empty())
+                .map(|_| format!("quiz-{:03}", index + 1)),
+        })
+        .collect();
+    let mut zip = ZipWriter::new(writer);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("imsmanifest.xml", options)?;
+    zip.write_all(manifest(volume, &tree, &entries).as_bytes())?;
+
+    zip.start_file("web_resources/style.css", options)?;
+    zip.write_all(STYLESHEET.as_bytes())?;
+
+    let language = volume_language(volume);
+    for entry in &entries {
+        let page = entry.page;
+        let body = format!(
+            "  <h1>{}</h1>\n{}",
+            escape(&page.title),
+            render_chunks(page)
+        );
+        zip.start_file(format!("web_resources/{}.html", entry.ident), options)?;
+        zip.write_all(xhtml_document(&page.title, language, "style.css", &body).as_bytes())?;
+
+        if let (Some(ident), Some(quiz)) = (&entry.quiz, &page.quiz) {
+            let assessment = quiz_to_qti(ident, &format!("Quiz: {}", page.title), quiz)
+                .context(format!("failed to convert quiz of page '{}'", page.slug))?;
+            zip.start_file(format!("{}/assessment.xml", ident), options)?;
+            zip.write_all(assessment.as_bytes())?;
+        }
+    }
+
+    zip.finish().context("failed to finish cartridge archive")?;
+    Ok(())
+}
+
+fn manifest(volume: &VolumeData, tree: &VolumeTree, entries: &[Entry]) -> String {
+    let page_items = |node: &PageNode, indent: &str| {
+        let Some(entry) = entries.iter().find(|e| e.page.slug == node.page.slug) else {
+            return String::new();
+        };
+        let mut items = format!(
+            "{indent}<item identifier=\"item-{}\" identifierref=\"{}\">\n{indent}  <title>{}</title>\n{indent}</item>\n",
+            entry.ident,
+            entry.ident,
+            escape(&entry.page.title)
+        );
+        if let Some(quiz) = &entry.quiz {
+            items.push_str(&format!(
+                "{indent}<item identifier=\"item-{}\" identifierref=\"{}\">\n{indent}  <title>Quiz: {}</title>\n{indent}</item>\n",
+                quiz,
+                quiz,
+                escape(&entry.page.title)
+            ));
+        }
+        items
+    };
+
+    let mut organization = String::new();
+    for node in &tree.nodes {
+        match node {
+            TreeNode::Chapter(chapter) => {
+                organization.push_str(&format!(
+                    "        <item identifier=\"chapter-{}\">\n          <title>{}</title>\n",
+                    chapter.order + 1,
+                    escape(chapter.title)
+                ));
+                for page in &chapter.pages {
+                    organization.push_str(&page_items(page, "          "));
+                }
+                organization.push_str("        </item>\n");
+            }
+            TreeNode::Page(page) => organization.push_str(&page_items(page, "        ")),
+        }
+    }
+
+    let mut resources = String::from(
+        r#"    <resource identifier="style" type="webcontent" href="web_resources/style.css">
+      <file href="web_resources/style.css"/>
+    </resource>
+"#,
+    );
+    for entry in entries {
+        resources.push_str(&format!(
+            r#"    <resource identifier="{ident}" type="webcontent" href="web_resources/{ident}.html">
+      <file href="web_resources/{ident}.html"/>
+      <dependency identifierref="style"/>
+    </resource>
+"#,
+            ident = entry.ident
+        ));
+        if let Some(quiz) = &entry.quiz {
+            resources.push_str(&format!(
+                r#"    <resource identifier="{quiz}" type="{QTI_RESOURCE_TYPE}">
+      <file href="{quiz}/assessment.xml"/>
+    </resource>
+"#
+            ));
+        }
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest identifier="{ident}" xmlns="http://www.imsglobal.org/xsd/imsccv1p1/imscp_v1p1" xmlns:lomimscc="http://ltsc.ieee.org/xsd/imsccv1p1/LOM/manifest" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.imsglobal.org/xsd/imsccv1p1/imscp_v1p1 http://www.imsglobal.org/profile/cc/ccv1p1/ccv1p1_imscp_v1p2_v1p0.xsd http://ltsc.ieee.org/xsd/imsccv1p1/LOM/manifest http://www.imsglobal.org/profile/cc/ccv1p1/LOM/ccv1p1_lommanifest_v1p0.xsd">
+  <metadata>
+    <schema>IMS Common Cartridge</schema>
+    <schemaversion>1.1.0</schemaversion>
+    <lomimscc:lom>
+      <lomimscc:general>
+        <lomimscc:title>
+          <lomimscc:string language="{lang}">{title}</lomimscc:string>
+        </lomimscc:title>
+        <lomimscc:description>
+          <lomimscc:string language="{lang}">{description}</lomimscc:string>
+        </lomimscc:description>
+      </lomimscc:general>
+    </lomimscc:lom>
+  </metadata>
+  <organizations>
+    <organization identifier="organization" structure="rooted-hierarchy">
+      <item identifier="root">
+{organization}      </item>
+    </organization>
+  </organizations>
+  <resources>
+{resources}  </resources>
+</manifest>
+"#,
+        ident = identifier("itell", &volume.slug),
+        lang = escape(volume_language(volume)),
+        title = escape(&volume.title),
+        description = escape(&volume.description),
+    )
+}
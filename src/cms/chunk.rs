@@ -1,14 +1,22 @@
+use std::str::FromStr;
+
 use serde::Serialize;
 
+use super::page::Difficulty;
+
 #[derive(Serialize, Debug)]
 pub struct ChunkData {
     pub title: String,
     pub slug: String,
     pub depth: usize,
     pub content: String,
-    pub cri: Option<CriItem>,
+    /// constructed response items, in authoring order
+    pub cri: Vec<CriItem>,
     pub show_header: bool,
     pub chunk_type: ChunkType,
+    pub reveal: RevealMode,
+    /// set for exercise chunks
+    pub exercise: Option<Exercise>,
 }
 
 #[derive(Serialize, Debug)]
@@ -19,11 +27,82 @@ pub enum ChunkType {
     Plain,
     #[serde(rename = "video")]
     Video,
+    #[serde(rename = "exercise")]
+    Exercise,
+}
+
+/// When a chunk is unblurred while reading
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RevealMode {
+    /// blurred until the reader finishes the previous chunk
+    #[default]
+    Standalone,
+    /// revealed together with the previous chunk, e.g. references and exercises
+    Auxiliary,
+    /// never blurred
+    AlwaysVisible,
+}
+
+impl RevealMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RevealMode::Standalone => "standalone",
+            RevealMode::Auxiliary => "auxiliary",
+            RevealMode::AlwaysVisible => "always_visible",
+        }
+    }
+}
+
+impl FromStr for RevealMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace(['-', ' '], "_").as_str() {
+            "standalone" => Ok(RevealMode::Standalone),
+            "auxiliary" | "auxiliary_to_previous" => Ok(RevealMode::Auxiliary),
+            "always_visible" | "visible" => Ok(RevealMode::AlwaysVisible),
+            other => anyhow::bail!("unknown reveal mode '{}'", other),
+        }
+    }
+}
+
+/// A code exercise, the chunk content holds its prompt
+#[derive(Serialize, Debug)]
+pub struct Exercise {
+    /// slug of the chunk the exercise belongs to
+    pub slug: String,
+    /// lowercase language name, e.g. "javascript" or "python"
+    pub language: String,
+    /// markdown task description
+    pub prompt: String,
+    /// code the editor starts with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starter_code: Option<String>,
+    /// tests run against the submission, not shown to the learner
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tests: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub solution: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
 pub struct CriItem {
+    /// Strapi component id, or the chunk slug for the single-field form
+    pub id: String,
     pub question: String,
     pub answer: String,
+    /// slug of the chunk the item belongs to
     pub slug: String,
+    /// free-form question type, e.g. "recall" or "inference"
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub question_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<Difficulty>,
+    /// grading guidance for reviewers and scoring
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rubric: Option<String>,
+    /// terms a good response is expected to mention
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
 }
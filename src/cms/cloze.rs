@@ -0,0 +1,723 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use super::{
+    assignment::Assignment,
+    chunk::ChunkType,
+    html::escape,
+    markdown::plain_text,
+    page::{ClozeGap, ClozeTest, PageData},
+    text::edit_distance,
+    validate::Diagnostic,
+};
+
+/// Function words skipped by [`ClozeStrategy::ContentWords`]: determiners, pronouns,
+/// prepositions, conjunctions and auxiliary verbs
+const FUNCTION_WORDS: &str =
+    "a about above after again against all am an and any are as at be because been before \
+    being below between both but by can could did do does doing down during each either \
+    every few for from further had has have having he her here hers herself him himself his \
+    how i if in into is it its itself just may me might more most must my myself neither no \
+    nor not now of off on once only or other our ours ourselves out over own same shall she \
+    should so some such than that the their theirs them themselves then there these they \
+    this those through to too under until up upon us very was we were what when where \
+    whether which while who whom whose why will with within without would yet you your yours \
+    yourself yourselves";
+
+/// Spelled-out numbers, excluded together with digits
+const NUMBER_WORDS: &str =
+    "zero one two three four five six seven eight nine ten eleven twelve twenty thirty forty \
+    fifty hundred thousand million billion first second third";
+
+/// How gap words are chosen
+#[derive(Debug, Clone)]
+pub enum ClozeStrategy {
+    /// every nth word, starting at a seeded offset
+    EveryNth(usize),
+    /// a random content word, skipping function words, in every `spacing` words
+    ContentWords,
+    /// a random word outside the given list of common words, in every `spacing` words
+    Frequency(HashSet<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ClozeOptions {
+    pub strategy: ClozeStrategy,
+    /// same seed and content give the same gaps
+    pub seed: u64,
+    /// words per gap for the content word and frequency strategies
+    pub spacing: usize,
+    pub max_gaps: Option<usize>,
+    /// skip capitalized words that don't start a sentence
+    pub exclude_names: bool,
+    /// skip words with digits and spelled-out numbers
+    pub exclude_numbers: bool,
+    /// shortest word to gap, in chars
+    pub min_length: usize,
+}
+
+impl Default for ClozeOptions {
+    fn default() -> Self {
+        Self {
+            strategy: ClozeStrategy::ContentWords,
+            seed: 0,
+            spacing: 7,
+            max_gaps: None,
+            exclude_names: true,
+            exclude_numbers: true,
+            min_length: 3,
+        }
+    }
+}
+
+/// A word of the original text, offsets in bytes
+struct Word<'a> {
+    start: usize,
+    end: usize,
+    text: &'a str,
+    /// first word of a sentence or paragraph
+    sentence_start: bool,
+}
+
+/// Generates cloze tests for pages that don't have one and adds the `cloze` assignment,
+/// returns how many were generated
+pub fn generate_cloze_tests(pages: &mut [PageData], options: &ClozeOptions) -> usize {
+    let mut generated = 0;
+    for page in pages.iter_mut().filter(|page| page.cloze_test.is_none()) {
+        page.cloze_test = generate_cloze(page, options);
+        if page.cloze_test.is_some() {
+            page.assignments
+                .push(Assignment::Cloze(page.assignment_settings.cloze));
+            generated += 1;
+        }
+    }
+    generated
+}
+
+/// Builds a cloze test from the prose of a page's regular chunks.
+///
+/// The original text has one paragraph per line. Each page gets its own random sequence
+/// derived from the seed and its slug, so adding a page doesn't change the others.
+/// Returns `None` when the page has no prose or no word qualifies.
+pub fn generate_cloze(page: &PageData, options: &ClozeOptions) -> Option<ClozeTest> {
+    let original_text = page
+        .chunks
+        .iter()
+        .filter(|chunk| matches!(chunk.chunk_type, ChunkType::Regular))
+        .map(|chunk| plain_text(&chunk.content))
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let words = split_words(&original_text);
+    let mut rng = SplitMix64::new(options.seed ^ fnv1a(&page.slug));
+    let eligible = |word: &Word| is_eligible(word, options);
+
+    let window = match options.strategy {
+        ClozeStrategy::EveryNth(n) => n,
+        _ => options.spacing,
+    }
+    .max(2);
+    let offset = rng.below(window);
+
+    let mut gaps = Vec::new();
+    let mut last_gap: Option<usize> = None;
+    for window_start in (0..words.len()).step_by(window) {
+        if options.max_gaps.is_some_and(|max| gaps.len() >= max) {
+            break;
+        }
+        let window_end = (window_start + window).min(words.len());
+        // keep at least one word between gaps
+        let first = match last_gap {
+            Some(index) => window_start.max(index + 2),
+            None => window_start,
+        };
+        if first >= window_end {
+            continue;
+        }
+
+        let chosen = match &options.strategy {
+            ClozeStrategy::EveryNth(_) => (first.max(window_start + offset)..window_end)
+                .find(|&index| eligible(&words[index])),
+            ClozeStrategy::ContentWords => {
+                let candidates: Vec<usize> = (first..window_end)
+                    .filter(|&index| {
+                        eligible(&words[index]) && !is_listed(FUNCTION_WORDS, words[index].text)
+                    })
+                    .collect();
+                rng.choose(&candidates)
+            }
+            ClozeStrategy::Frequency(common) => {
+                let candidates: Vec<usize> = (first..window_end)
+                    .filter(|&index| {
+                        eligible(&words[index])
+                            && !common.contains(&words[index].text.to_lowercase())
+                    })
+                    .collect();
+                rng.choose(&candidates)
+            }
+        };
+
+        if let Some(index) = chosen {
+            let word = &words[index];
+            gaps.push(ClozeGap {
+                start: word.start,
+                end: word.end,
+                gapped_text: "_".repeat(word.text.chars().count()),
+                original_word: Some(word.text.to_string()),
+            });
+            last_gap = Some(index);
+        }
+    }
+
+    if gaps.is_empty() {
+        return None;
+    }
+    Some(ClozeTest {
+        original_text,
+        gaps,
+    })
+}
+
+fn is_eligible(word: &Word, options: &ClozeOptions) -> bool {
+    if word.text.chars().count() < options.min_length {
+        return false;
+    }
+    // contractions and possessives are hard to fill in exactly
+    if word.text.contains(['\'', '’']) {
+        return false;
+    }
+    if options.exclude_numbers
+        && (word.text.chars().any(|c| c.is_ascii_digit()) || is_listed(NUMBER_WORDS, word.text))
+    {
+        return false;
+    }
+    if options.exclude_names
+        && !word.sentence_start
+        && word.text.chars().next().is_some_and(char::is_uppercase)
+    {
+        return false;
+    }
+    true
+}
+
+/// Whether a word is in a whitespace separated list, ignoring case
+fn is_listed(list: &str, word: &str) -> bool {
+    list.split_whitespace()
+        .any(|listed| listed.eq_ignore_ascii_case(word))
+}
+
+/// Splits text into words, letters and digits joined by inner apostrophes or hyphens
+fn split_words(text: &str) -> Vec<Word<'_>> {
+    let mut words = Vec::new();
+    let mut sentence_start = true;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if !c.is_alphanumeric() {
+            if matches!(c, '.' | '!' | '?' | '\n') {
+                sentence_start = true;
+            }
+            continue;
+        }
+
+        let mut end = start + c.len_utf8();
+        while let Some(&(index, next)) = chars.peek() {
+            if next.is_alphanumeric() {
+                end = index + next.len_utf8();
+                chars.next();
+            } else if matches!(next, '\'' | '’' | '-')
+                && text[index + next.len_utf8()..]
+                    .chars()
+                    .next()
+                    .is_some_and(char::is_alphanumeric)
+            {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        words.push(Word {
+            start,
+            end,
+            text: &text[start..end],
+            sentence_start,
+        });
+        sentence_start = false;
+    }
+
+    words
+}
+
+/// Small seeded generator, the sequence only depends on the seed
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..n`, `n` must not be zero
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn choose(&mut self, items: &[usize]) -> Option<usize> {
+        (!items.is_empty()).then(|| items[self.below(items.len())])
+    }
+}
+
+/// Stable hash of a page slug, `DefaultHasher` may change between Rust versions
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3)
+    })
+}
+
+/// Checks cloze test gaps against the original text.
+///
+/// Gap offsets are byte offsets into `original_text`. They must fall on char boundaries,
+/// be sorted and not overlap, and `original_word` must be the text the gap spans.
+pub fn validate_cloze_tests(pages: &[PageData]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for page in pages {
+        let Some(cloze_test) = &page.cloze_test else {
+            continue;
+        };
+        let text = cloze_test.original_text.as_str();
+        let error = |message: String| Diagnostic::error(Some(&page.slug), None, message);
+
+        if text.trim().is_empty() {
+            diagnostics.push(error("Cloze test has no original text".to_string()));
+            continue;
+        }
+        if cloze_test.gaps.is_empty() {
+            diagnostics.push(Diagnostic::warning(
+                Some(&page.slug),
+                None,
+                "Cloze test has no gaps".to_string(),
+            ));
+            continue;
+        }
+
+        // end of the previous valid gap, later gaps must start after it
+        let mut previous: Option<(usize, usize)> = None;
+        for (index, gap) in cloze_test.gaps.iter().enumerate() {
+            let label = format!("Cloze gap {} ({}..{})", index + 1, gap.start, gap.end);
+
+            if gap.start >= gap.end {
+                diagnostics.push(error(format!("{} is empty or reversed", label)));
+                continue;
+            }
+            if gap.end > text.len() {
+                diagnostics.push(error(format!(
+                    "{} ends past the original text, which is {} bytes long",
+                    label,
+                    text.len()
+                )));
+                continue;
+            }
+            if !text.is_char_boundary(gap.start) || !text.is_char_boundary(gap.end) {
+                diagnostics.push(error(format!(
+                    "{} splits a character, offsets must be byte offsets on char boundaries",
+                    label
+                )));
+                continue;
+            }
+
+            match previous {
+                Some((start, _)) if gap.start < start => {
+                    diagnostics.push(error(format!(
+                        "{} comes before the previous gap, gaps must be sorted by offset",
+                        label
+                    )));
+                }
+                Some((start, end)) if gap.start < end => {
+                    diagnostics.push(error(format!(
+                        "{} overlaps the previous gap ({}..{})",
+                        label, start, end
+                    )));
+                }
+                _ => {}
+            }
+            previous = Some((gap.start, gap.end));
+
+            let spanned = &text[gap.start..gap.end];
+            if let Some(word) = &gap.original_word {
+                if word != spanned {
+                    let message = format!(
+                        "{} spans '{}' but its original word is '{}'",
+                        label, spanned, word
+                    );
+                    // whitespace and case differences don't break the frontend
+                    diagnostics.push(
+                        if word.trim().to_lowercase() == spanned.trim().to_lowercase() {
+                            Diagnostic::warning(Some(&page.slug), None, message)
+                        } else {
+                            error(message)
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// How strictly learner responses are compared with the original words
+#[derive(Debug, Clone, Copy)]
+pub struct ClozeTolerance {
+    pub ignore_case: bool,
+    /// edits accepted as typos, capped at one per four letters of the expected word
+    pub max_typos: usize,
+}
+
+impl Default for ClozeTolerance {
+    fn default() -> Self {
+        Self {
+            ignore_case: true,
+            max_typos: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapResult {
+    Correct,
+    /// accepted within the typo tolerance
+    Typo,
+    Wrong,
+    /// no response, or only whitespace
+    Blank,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClozeScore {
+    /// one result per gap, in gap order
+    pub gaps: Vec<GapResult>,
+}
+
+impl ClozeScore {
+    /// Gaps answered correctly or within the typo tolerance
+    pub fn correct(&self) -> usize {
+        self.gaps
+            .iter()
+            .filter(|result| matches!(result, GapResult::Correct | GapResult::Typo))
+            .count()
+    }
+
+    /// Share of correct gaps, 0 for a test without gaps
+    pub fn ratio(&self) -> f64 {
+        if self.gaps.is_empty() {
+            return 0.0;
+        }
+        self.correct() as f64 / self.gaps.len() as f64
+    }
+}
+
+/// Renders the passage as markdown, one paragraph per line of the original text, with an
+/// `i-cloze-gap` element carrying the gap index and expected word length in place of each gap
+pub fn render_cloze_markdown(test: &ClozeTest) -> String {
+    render_passage(test, escape_markdown, "\n\n")
+}
+
+/// Renders the passage as HTML paragraphs with `i-cloze-gap` elements, like
+/// [`render_cloze_markdown`]
+pub fn render_cloze_html(test: &ClozeTest) -> String {
+    format!("<p>{}</p>", render_passage(test, escape, "</p>\n<p>"))
+}
+
+/// Scores learner responses against the original words, the nth response fills the nth gap.
+/// Missing responses count as blank.
+pub fn score_cloze<S: AsRef<str>>(
+    test: &ClozeTest,
+    responses: &[S],
+    tolerance: ClozeTolerance,
+) -> ClozeScore {
+    let gaps = test
+        .gaps
+        .iter()
+        .enumerate()
+        .map(|(index, gap)| {
+            let response = responses.get(index).map_or("", |r| r.as_ref().trim());
+            score_gap(expected_word(test, gap), response, tolerance)
+        })
+        .collect();
+    ClozeScore { gaps }
+}
+
+fn score_gap(expected: &str, response: &str, tolerance: ClozeTolerance) -> GapResult {
+    if response.is_empty() {
+        return GapResult::Blank;
+    }
+
+    let (expected, response) = if tolerance.ignore_case {
+        (expected.to_lowercase(), response.to_lowercase())
+    } else {
+        (expected.to_string(), response.to_string())
+    };
+    if expected == response {
+        return GapResult::Correct;
+    }
+
+    let allowed = tolerance.max_typos.min(expected.chars().count() / 4);
+    if edit_distance(&expected, &response) <= allowed {
+        GapResult::Typo
+    } else {
+        GapResult::Wrong
+    }
+}
+
+/// The word a gap hides, its `original_word` or else the text it spans
+fn expected_word<'a>(test: &'a ClozeTest, gap: &'a ClozeGap) -> &'a str {
+    gap.original_word
+        .as_deref()
+        .or_else(|| test.original_text.get(gap.start..gap.end))
+        .unwrap_or_default()
+        .trim()
+}
+
+/// Writes the original text with gaps replaced, skipping gaps that are out of order or
+/// don't fall on char boundaries
+fn render_passage(
+    test: &ClozeTest,
+    escape_text: fn(&str) -> String,
+    paragraph_break: &str,
+) -> String {
+    let text = &test.original_text;
+    let push_text = |output: &mut String, text: &str| {
+        let paragraphs: Vec<String> = text.split('\n').map(escape_text).collect();
+        output.push_str(&paragraphs.join(paragraph_break));
+    };
+
+    let mut output = String::with_capacity(text.len() + 40 * test.gaps.len());
+    let mut cursor = 0;
+    for (index, gap) in test.gaps.iter().enumerate() {
+        if gap.start < cursor || text.get(gap.start..gap.end).is_none() {
+            continue;
+        }
+        push_text(&mut output, &text[cursor..gap.start]);
+        output.push_str(&format!(
+            "<i-cloze-gap index=\"{}\" length=\"{}\"></i-cloze-gap>",
+            index,
+            expected_word(test, gap).chars().count()
+        ));
+        cursor = gap.end;
+    }
+    push_text(&mut output, &text[cursor..]);
+
+    output
+}
+
+/// Escapes chars that markdown would otherwise read as formatting, HTML or MDX-style props
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '{' | '}' | '<' | '>' | '#' | '&' | '|' | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::chunk::{ChunkData, RevealMode};
+
+    const TEXT: &str = "The river carried small boats past the quiet market every morning. \
+        Traders unloaded baskets of apples, onions and fresh bread while children watched \
+        from the old stone bridge above the water.";
+
+    fn page(content: &str) -> PageData {
+        PageData {
+            title: "Page".to_string(),
+            slug: "page".to_string(),
+            parent: None,
+            description: None,
+            keywords: vec![],
+            cover_image: None,
+            order: 0,
+            free: false,
+            assignments: vec![],
+            assignment_settings: Default::default(),
+            quiz: None,
+            cloze_test: None,
+            chunks: vec![ChunkData {
+                title: "Chunk".to_string(),
+                slug: "chunk".to_string(),
+                depth: 0,
+                content: content.to_string(),
+                cri: vec![],
+                show_header: true,
+                chunk_type: ChunkType::Regular,
+                reveal: RevealMode::Standalone,
+                exercise: None,
+            }],
+        }
+    }
+
+    fn gap_words(test: &ClozeTest) -> Vec<&str> {
+        test.gaps
+            .iter()
+            .map(|gap| &test.original_text[gap.start..gap.end])
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_gaps() {
+        let page = page(TEXT);
+        let options = ClozeOptions {
+            seed: 42,
+            ..ClozeOptions::default()
+        };
+        let first = generate_cloze(&page, &options).unwrap();
+        let second = generate_cloze(&page, &options).unwrap();
+        assert_eq!(gap_words(&first), gap_words(&second));
+
+        for gap in &first.gaps {
+            let word = &first.original_text[gap.start..gap.end];
+            assert_eq!(gap.original_word.as_deref(), Some(word));
+            assert_eq!(gap.gapped_text, "_".repeat(word.chars().count()));
+            assert!(!is_listed(FUNCTION_WORDS, word));
+        }
+    }
+
+    #[test]
+    fn every_nth_word_is_gapped() {
+        let page = page("alpha bravo charlie delta echo foxtrot golf hotel india juliet kilo lima");
+        let options = ClozeOptions {
+            strategy: ClozeStrategy::EveryNth(3),
+            ..ClozeOptions::default()
+        };
+        let test = generate_cloze(&page, &options).unwrap();
+        let words: Vec<&str> = test.original_text.split(' ').collect();
+        let indices: Vec<usize> = gap_words(&test)
+            .iter()
+            .map(|gap| words.iter().position(|word| word == gap).unwrap())
+            .collect();
+
+        assert_eq!(indices.len(), 4);
+        assert!(indices[0] < 3);
+        assert!(indices.windows(2).all(|pair| pair[1] - pair[0] == 3));
+    }
+
+    #[test]
+    fn frequency_gaps_only_uncommon_words() {
+        let common: HashSet<String> = TEXT
+            .split(|c: char| !c.is_alphabetic())
+            .map(str::to_lowercase)
+            .filter(|word| word != "baskets" && word != "bridge")
+            .collect();
+        let options = ClozeOptions {
+            strategy: ClozeStrategy::Frequency(common),
+            ..ClozeOptions::default()
+        };
+        let test = generate_cloze(&page(TEXT), &options).unwrap();
+        assert_eq!(gap_words(&test), ["baskets", "bridge"]);
+    }
+
+    fn test_with(words: &[&str]) -> ClozeTest {
+        let original_text = words.join(" ");
+        let mut gaps = Vec::new();
+        let mut start = 0;
+        for word in words {
+            gaps.push(ClozeGap {
+                start,
+                end: start + word.len(),
+                gapped_text: "_".repeat(word.len()),
+                original_word: Some(word.to_string()),
+            });
+            start += word.len() + 1;
+        }
+        ClozeTest {
+            original_text,
+            gaps,
+        }
+    }
+
+    #[test]
+    fn scoring_ignores_case_by_default() {
+        let test = test_with(&["Apple"]);
+        let score = score_cloze(&test, &["apple"], ClozeTolerance::default());
+        assert_eq!(score.gaps, [GapResult::Correct]);
+
+        let strict = ClozeTolerance {
+            ignore_case: false,
+            max_typos: 0,
+        };
+        assert_eq!(
+            score_cloze(&test, &["apple"], strict).gaps,
+            [GapResult::Wrong]
+        );
+    }
+
+    #[test]
+    fn scoring_accepts_a_typo_in_longer_words() {
+        let test = test_with(&["banana", "cat", "elephant", "river", "stone"]);
+        let responses = ["banena", "cot", "elefant", " River ", ""];
+        let score = score_cloze(&test, &responses, ClozeTolerance::default());
+        assert_eq!(
+            score.gaps,
+            [
+                GapResult::Typo,
+                GapResult::Wrong,
+                GapResult::Wrong,
+                GapResult::Correct,
+                GapResult::Blank
+            ]
+        );
+        assert_eq!(score.correct(), 2);
+
+        // at most one typo per four letters, however many are allowed
+        let lenient = ClozeTolerance {
+            ignore_case: true,
+            max_typos: 5,
+        };
+        let score = score_cloze(&test, &responses, lenient);
+        assert_eq!(score.gaps[1], GapResult::Wrong);
+        assert_eq!(score.gaps[2], GapResult::Typo);
+    }
+
+    #[test]
+    fn missing_responses_are_blank() {
+        let test = test_with(&["river", "stone"]);
+        let score = score_cloze(&test, &["river"], ClozeTolerance::default());
+        assert_eq!(score.gaps, [GapResult::Correct, GapResult::Blank]);
+        assert_eq!(score.ratio(), 0.5);
+    }
+
+    #[test]
+    fn markdown_passage_escapes_braces() {
+        let test = ClozeTest {
+            original_text: "Write {name} in a set".to_string(),
+            gaps: vec![ClozeGap {
+                start: 18,
+                end: 21,
+                gapped_text: "___".to_string(),
+                original_word: Some("set".to_string()),
+            }],
+        };
+        assert_eq!(
+            render_cloze_markdown(&test),
+            "Write \\{name\\} in a <i-cloze-gap index=\"0\" length=\"3\"></i-cloze-gap>"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{assignment::AssignmentSettings, text::edit_distance, validate::Diagnostic};
+
+/// Keys written from the volume itself, a config must not override them
+pub const RESERVED_KEYS: &[&str] = &["title", "slug", "description", "free_pages", "summary"];
+
+/// Typed keys of `VolumeConfig`, used for typo detection
+const KNOWN_KEYS: &[&str] = &["language", "authors", "license", "cover_image", "assignments"];
+
+/// Volume configuration from Strapi, flattened into `volume.yaml`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VolumeConfig {
+    /// BCP 47 language tag of the content, e.g. `en` or `en-US`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+
+    /// URL of the cover image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_image: Option<String>,
+
+    /// default assignment settings, pages may override them with `AssignmentConfig`
+    #[serde(default, skip_serializing_if = "AssignmentSettings::is_empty")]
+    pub assignments: AssignmentSettings,
+
+    /// other keys, passed through to `volume.yaml` as is
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+impl VolumeConfig {
+    /// Parses and validates the `VolumeConfig` JSON of a volume
+    pub fn parse(value: &Value) -> Result<Self> {
+        let object = value
+            .as_object()
+            .context("VolumeConfig must be a JSON object")?;
+
+        let reserved: Vec<&str> = object
+            .keys()
+            .map(String::as_str)
+            .filter(|key| RESERVED_KEYS.contains(key))
+            .collect();
+        if !reserved.is_empty() {
+            bail!(
+                "VolumeConfig sets reserved keys '{}', these are taken from the volume itself",
+                reserved.join("', '")
+            );
+        }
+
+        let config: VolumeConfig =
+            serde_json::from_value(value.clone()).context("VolumeConfig has invalid values")?;
+
+        if let Some(language) = &config.language {
+            let tag = Regex::new(r"^[A-Za-z]{2,3}(-[A-Za-z0-9]{2,8})*$").unwrap();
+            if !tag.is_match(language) {
+                bail!("VolumeConfig language '{}' is not a BCP 47 tag", language);
+            }
+        }
+
+        config
+            .assignments
+            .check()
+            .context("VolumeConfig has invalid assignments")?;
+
+        Ok(config)
+    }
+
+    /// Warns about passthrough keys that look like typos of a known key
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        self.extra
+            .keys()
+            .filter_map(|key| {
+                let known = similar_key(key)?;
+                Some(Diagnostic::warning(
+                    None,
+                    None,
+                    format!(
+                        "VolumeConfig has unknown key '{}', did you mean '{}'? It is passed through as is",
+                        key, known
+                    ),
+                ))
+            })
+            .collect()
+    }
+
+    /// JSON Schema of the config, so authors can validate it before publishing
+    pub fn schema() -> Value {
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "VolumeConfig",
+            "description": "iTELL volume configuration, keys are flattened into volume.yaml",
+            "type": "object",
+            "properties": {
+                "language": {
+                    "type": "string",
+                    "description": "BCP 47 language tag of the content, e.g. en or en-US",
+                    "pattern": "^[A-Za-z]{2,3}(-[A-Za-z0-9]{2,8})*$"
+                },
+                "authors": {
+                    "type": "array",
+                    "items": { "type": "string" }
+                },
+                "license": { "type": "string" },
+                "cover_image": {
+                    "type": "string",
+                    "description": "URL of the cover image"
+                },
+                "assignments": {
+                    "type": "object",
+                    "description": "Default assignment settings, pages may override them with AssignmentConfig",
+                    "properties": {
+                        "summary": {
+                            "type": "object",
+                            "properties": {
+                                "min_words": { "type": "integer", "minimum": 0 },
+                                "max_words": { "type": "integer", "minimum": 0 }
+                            },
+                            "additionalProperties": false
+                        },
+                        "quiz": {
+                            "type": "object",
+                            "properties": {
+                                "pass_threshold": {
+                                    "type": "number",
+                                    "description": "Share of correct answers needed to pass",
+                                    "minimum": 0,
+                                    "maximum": 1
+                                }
+                            },
+                            "additionalProperties": false
+                        },
+                        "cloze": {
+                            "type": "object",
+                            "properties": {
+                                "max_attempts": { "type": "integer", "minimum": 1 }
+                            },
+                            "additionalProperties": false
+                        }
+                    },
+                    "additionalProperties": false
+                }
+            },
+            "not": {
+                "anyOf": RESERVED_KEYS
+                    .iter()
+                    .map(|key| json!({ "required": [key] }))
+                    .collect::<Vec<_>>()
+            },
+            "additionalProperties": true
+        })
+    }
+}
+
+/// Returns the known or reserved key that `key` is likely a typo of
+fn similar_key(key: &str) -> Option<&'static str> {
+    if key.chars().count() <= 3 {
+        return None;
+    }
+
+    KNOWN_KEYS
+        .iter()
+        .chain(RESERVED_KEYS)
+        .find(|known| **known != key && edit_distance(key, known) <= 2)
+        .copied()
+}
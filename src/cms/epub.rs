@@ -0,0 +1,335 @@
+use std::{
+    io::{Seek, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use super::{
+    fetch::VolumeData,
+    frontmatter::Heading,
+    html::{escape, render_chunks, volume_language, xhtml_document, STYLESHEET},
+    markdown::{page_slugger, transform_headings},
+    page::{PageData, QuestionType},
+    tree::{PageNode, TreeNode, VolumeTree},
+};
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+/// Writes the volume as an EPUB 3 file.
+///
+/// Pages are grouped under their chapters in the navigation document, which also
+/// lists chunks and their headings. Videos and remote images become links, and
+/// constructed responses and quizzes are collected as review questions at the end of each page.
+pub fn export_epub<W: Write + Seek>(
+    volume: &VolumeData,
+    pages: &[PageData],
+    writer: W,
+) -> Result<()> {
+    let tree = VolumeTree::build(pages);
+    let files: Vec<(String, &PageData)> = tree
+        .pages()
+        .enumerate()
+        .map(|(index, node)| (format!("page-{:03}.xhtml", index + 1), node.page))
+        .collect();
+    let file_name = |page: &PageData| {
+        files
+            .iter()
+            .find(|(_, p)| p.slug == page.slug)
+            .map(|(name, _)| name.as_str())
+            .unwrap_or_default()
+    };
+
+    let mut zip = ZipWriter::new(writer);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // the mimetype must be the first entry and uncompressed
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+
+    zip.start_file("OEBPS/style.css", deflated)?;
+    zip.write_all(STYLESHEET.as_bytes())?;
+
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(package_document(volume, &files).as_bytes())?;
+
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(navigation_document(volume, &tree, file_name).as_bytes())?;
+
+    let language = volume_language(volume);
+    for (name, page) in &files {
+        zip.start_file(format!("OEBPS/{}", name), deflated)?;
+        zip.write_all(page_document(page, language).as_bytes())
+            .context(format!("failed to write page '{}'", page.slug))?;
+    }
+
+    zip.finish().context("failed to finish EPUB archive")?;
+    Ok(())
+}
+
+fn package_document(volume: &VolumeData, files: &[(String, &PageData)]) -> String {
+    let mut metadata = format!(
+        r#"    <dc:identifier id="book-id">urn:itell:{}</dc:identifier>
+    <dc:title>{}</dc:title>
+    <dc:language>{}</dc:language>
+    <dc:description>{}</dc:description>
+    <meta property="dcterms:modified">{}</meta>
+"#,
+        escape(&volume.slug),
+        escape(&volume.title),
+        escape(volume_language(volume)),
+        escape(&volume.description),
+        utc_timestamp()
+    );
+    if let Some(config) = &volume.volume_config {
+        for author in &config.authors {
+            metadata.push_str(&format!(
+                "    <dc:creator>{}</dc:creator>\n",
+                escape(author)
+            ));
+        }
+        if let Some(license) = &config.license {
+            metadata.push_str(&format!("    <dc:rights>{}</dc:rights>\n", escape(license)));
+        }
+    }
+
+    let mut manifest = String::from(
+        r#"    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="style" href="style.css" media-type="text/css"/>
+"#,
+    );
+    let mut spine = String::new();
+    for (index, (name, _)) in files.iter().enumerate() {
+        manifest.push_str(&format!(
+            "    <item id=\"page-{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            index + 1,
+            name
+        ));
+        spine.push_str(&format!("    <itemref idref=\"page-{}\"/>\n", index + 1));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+{metadata}  </metadata>
+  <manifest>
+{manifest}  </manifest>
+  <spine>
+{spine}  </spine>
+</package>
+"#
+    )
+}
+
+fn navigation_document<'a>(
+    volume: &VolumeData,
+    tree: &VolumeTree,
+    file_name: impl Fn(&PageData) -> &'a str,
+) -> String {
+    let page_entry = |node: &PageNode, indent: &str| {
+        let href = file_name(node.page);
+        let mut entry = format!(
+            "{indent}<li><a href=\"{}\">{}</a>",
+            href,
+            escape(&node.page.title)
+        );
+
+        let mut slugger = page_slugger(node.page);
+        let chunks: Vec<String> = node
+            .page
+            .chunks
+            .iter()
+            .map(|chunk| {
+                let (_, headings) = transform_headings(&chunk.content, &mut slugger);
+                format!(
+                    "<li><a href=\"{}#{}\">{}</a>{}</li>",
+                    href,
+                    escape(&chunk.slug),
+                    escape(&chunk.title),
+                    heading_list(href, &headings)
+                )
+            })
+            .collect();
+        if !chunks.is_empty() {
+            entry.push_str(&format!("<ol>{}</ol>", chunks.concat()));
+        }
+        entry.push_str("</li>\n");
+        entry
+    };
+
+    let mut items = String::new();
+    for node in &tree.nodes {
+        match node {
+            TreeNode::Chapter(chapter) => {
+                items.push_str(&format!(
+                    "      <li><span>{}</span>\n        <ol>\n",
+                    escape(chapter.title)
+                ));
+                for page in &chapter.pages {
+                    items.push_str(&page_entry(page, "          "));
+                }
+                items.push_str("        </ol>\n      </li>\n");
+            }
+            TreeNode::Page(page) => items.push_str(&page_entry(page, "      ")),
+        }
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" lang="{lang}" xml:lang="{lang}">
+<head>
+  <title>{title}</title>
+</head>
+<body>
+  <nav epub:type="toc" id="toc">
+    <h1>{title}</h1>
+    <ol>
+{items}    </ol>
+  </nav>
+</body>
+</html>
+"#,
+        lang = escape(volume_language(volume)),
+        title = escape(&volume.title),
+    )
+}
+
+fn heading_list(href: &str, headings: &[Heading]) -> String {
+    if headings.is_empty() {
+        return String::new();
+    }
+
+    let items: String = headings
+        .iter()
+        .map(|heading| {
+            format!(
+                "<li><a href=\"{}#{}\">{}</a>{}</li>",
+                href,
+                escape(&heading.slug),
+                escape(&heading.title),
+                heading_list(href, &heading.children)
+            )
+        })
+        .collect();
+    format!("<ol>{}</ol>", items)
+}
+
+fn page_document(page: &PageData, language: &str) -> String {
+    let mut body = format!("  <h1>{}</h1>\n", escape(&page.title));
+    body.push_str(&render_chunks(page));
+    body.push_str(&review_section(page));
+    xhtml_document(&page.title, language, "style.css", &body)
+}
+
+/// Constructed response and quiz questions of a page, followed by their answers
+fn review_section(page: &PageData) -> String {
+    let cri: Vec<_> = page.chunks.iter().flat_map(|c| &c.cri).collect();
+    let quiz = page.quiz.as_deref().unwrap_or_default();
+    if cri.is_empty() && quiz.is_empty() {
+        return String::new();
+    }
+
+    let mut questions = String::new();
+    let mut answers = String::new();
+
+    for item in &cri {
+        questions.push_str(&format!(
+            "      <li><p>{}</p></li>\n",
+            escape(&item.question)
+        ));
+        answers.push_str(&format!("      <li><p>{}</p></li>\n", escape(&item.answer)));
+    }
+
+    for item in quiz {
+        // short answers have no choices to list
+        let options: String = match item.question_type {
+            QuestionType::ShortAnswer => String::new(),
+            _ => format!(
+                "<ol type=\"a\">{}</ol>",
+                item.answers
+                    .iter()
+                    .map(|a| format!("<li>{}</li>", escape(&a.answer)))
+                    .collect::<String>()
+            ),
+        };
+        questions.push_str(&format!(
+            "      <li><p>{}</p>{}</li>\n",
+            escape(&item.question),
+            options
+        ));
+
+        let correct: Vec<String> = item
+            .answers
+            .iter()
+            .filter(|a| a.correct)
+            .map(|a| escape(&a.answer))
+            .collect();
+        let explanation = item
+            .explanation
+            .as_deref()
+            .map(|e| format!("<p>{}</p>", escape(e)))
+            .unwrap_or_default();
+        answers.push_str(&format!(
+            "      <li><p>{}</p>{}</li>\n",
+            correct.join("; "),
+            explanation
+        ));
+    }
+
+    format!(
+        r#"  <section class="review">
+    <h2>Review Questions</h2>
+    <ol>
+{questions}    </ol>
+    <section class="answers">
+      <h3>Answers</h3>
+      <ol>
+{answers}      </ol>
+    </section>
+  </section>
+"#
+    )
+}
+
+/// Current time as `YYYY-MM-DDThh:mm:ssZ`, required by `dcterms:modified`
+fn utc_timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+
+    // civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
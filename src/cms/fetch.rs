@@ -1,32 +1,48 @@
 use anyhow::{Context, Result};
-use regex::Regex;
+use serde::Deserialize;
 use serde_json::Value;
 use std::{collections::BTreeMap, str::FromStr};
 use thiserror::Error;
 
 use super::{
-    chunk::{ChunkData, ChunkType, CriItem},
-    frontmatter::{ChunkMeta, Frontmatter, Heading},
-    page::{PageData, PageParent, QuizAnswerItem, QuizItem, ClozeTest},
+    access::resolve_free_pages,
+    assignment::{assignment_config, Assignment, AssignmentSettings},
+    chunk::{ChunkData, ChunkType, CriItem, Exercise, RevealMode},
+    cloze::render_cloze_markdown,
+    config::VolumeConfig,
+    frontmatter::{ChunkMeta, Frontmatter, VolumeFrontmatter},
+    html::escape,
+    markdown::{page_slugger, plain_text, transform_headings},
+    navigation::PageNavigation,
+    page::{ClozeTest, Difficulty, PageData, PageParent, QuestionType, QuizAnswerItem, QuizItem},
+    stats::page_stats,
+    text::lead_sentences,
 };
 
 const BASE_URL: &str = "https://itell-strapi-um5h.onrender.com/api/texts/";
-const QUERY: &str = "?populate%5BPages%5D%5Bfields%5D%5B0%5D=%2A&populate%5BPages%5D%5Bsort%5D=createdAt&populate%5BPages%5D%5Bpopulate%5D%5BContent%5D=true&populate%5BPages%5D%5Bpopulate%5D%5BChapter%5D%5Bfields%5D%5B0%5D=Title&populate%5BPages%5D%5Bpopulate%5D%5BChapter%5D%5Bfields%5D%5B1%5D=Slug&populate%5BPages%5D%5Bpopulate%5D%5BQuiz%5D%5Bpopulate%5D%5BQuestions%5D%5Bpopulate%5D=%2A";
+/// Populates pages with their chapter, quiz, cover image and content. Strapi only populates a
+/// dynamic zone one level deep, so `Content` populates every chunk component as well, which
+/// fetches nested components such as `ConstructedResponses`.
+const QUERY: &str = "?populate%5BPages%5D%5Bfields%5D%5B0%5D=%2A&populate%5BPages%5D%5Bsort%5D=createdAt&populate%5BPages%5D%5Bpopulate%5D%5BContent%5D%5Bpopulate%5D=%2A&populate%5BPages%5D%5Bpopulate%5D%5BChapter%5D%5Bfields%5D%5B0%5D=Title&populate%5BPages%5D%5Bpopulate%5D%5BChapter%5D%5Bfields%5D%5B1%5D=Slug&populate%5BPages%5D%5Bpopulate%5D%5BQuiz%5D%5Bpopulate%5D%5BQuestions%5D%5Bpopulate%5D=%2A&populate%5BPages%5D%5Bpopulate%5D%5BCoverImage%5D=true";
+/// Length of an extracted page description in chars, about a search result snippet
+const DESCRIPTION_LENGTH: usize = 160;
 
 pub struct VolumeData {
     pub title: String,
     pub description: String,
     pub slug: String,
+    /// raw `FreePages` entries: page slugs, chapter slugs or `first..last` ranges
     pub free_pages: Vec<String>,
     pub summary: Option<String>,
-    pub volume_config: Option<serde_json::Value>,
-    pages: Vec<serde_json::Value>,
+    pub volume_config: Option<VolumeConfig>,
+    /// page entries as returned by Strapi, turned into `PageData` by `collect_pages`
+    pub pages: Vec<serde_json::Value>,
 }
 
 #[derive(Error, Debug)]
 enum RequestError {
     #[error("HTTP request failed: {0}")]
-    Http(#[from] ureq::Error),
+    Http(#[from] Box<ureq::Error>),
 
     #[error("Failed to read response body: {0}")]
     IO(#[from] std::io::Error),
@@ -55,7 +71,7 @@ pub fn get_volume_data(volume_id: &str) -> Result<VolumeData> {
         .call()
         .map_err(|e| match e {
             ureq::Error::Status(code, _) => RequestError::StrapiServer { status: code },
-            other => RequestError::Http(other),
+            other => RequestError::Http(Box::new(other)),
         })
         .context("Connecting to Strapi API")?;
 
@@ -75,7 +91,11 @@ pub fn get_volume_data(volume_id: &str) -> Result<VolumeData> {
         .context("No pages in volume response")?
         .to_owned();
 
-    let volume_config = data.get("VolumeConfig").cloned();
+    let volume_config = data
+        .get("VolumeConfig")
+        .filter(|c| !c.is_null())
+        .map(VolumeConfig::parse)
+        .transpose()?;
 
     Ok(VolumeData {
         title: get_attribute(data, "Title").context("Volume must set title")?,
@@ -88,36 +108,66 @@ pub fn get_volume_data(volume_id: &str) -> Result<VolumeData> {
     })
 }
 
+/// Constructed response items of a chunk, from the `Question`/`ConstructedResponse` fields
+/// followed by the repeatable `ConstructedResponses` component
+fn parse_cri(chunk: &Value, chunk_slug: &str) -> Result<Vec<CriItem>> {
+    let mut items = Vec::new();
+
+    if let (Some(question), Some(answer)) = (
+        get_attribute::<String>(chunk, "Question"),
+        get_attribute::<String>(chunk, "ConstructedResponse"),
+    ) {
+        items.push(CriItem {
+            id: chunk_slug.to_string(),
+            question,
+            answer,
+            slug: chunk_slug.to_string(),
+            question_type: None,
+            difficulty: None,
+            rubric: None,
+            keywords: Vec::new(),
+        });
+    }
 
-/// Transforms markdown content by adding IDs to H3 headings
-fn transform_headings(content: &str, headings: &mut Vec<Heading>) -> String {
-    let heading_regex = Regex::new(r"(?m)^### (.+)$").unwrap();
-    let mut slugger = github_slugger::Slugger::default();
-
-    heading_regex
-        .replace_all(content, |caps: &regex::Captures| {
-            let heading_title = &caps[1];
-            let id = slugger.slug(heading_title);
-            headings.push(Heading {
-                slug: id.clone(),
-                title: heading_title.to_string(),
-                level: 3,
-            });
-            format!("### {} {{#{}}}", heading_title, id)
-        })
-        .to_string()
-}
-
-/// Parses a question-answer pair from a chunk
-fn parse_cri(chunk: &Value, chunk_slug: &str) -> Option<CriItem> {
-    let question = get_attribute::<String>(chunk, "Question")?;
-    let answer = get_attribute::<String>(chunk, "ConstructedResponse")?;
+    let components = chunk
+        .get("ConstructedResponses")
+        .and_then(|c| c.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    for (index, component) in components.iter().enumerate() {
+        // numbered after the chunk when Strapi doesn't provide an id
+        let id = get_attribute::<String>(component, "id")
+            .unwrap_or_else(|| format!("{}-{}", chunk_slug, index + 1));
+
+        let question = get_attribute::<String>(component, "Question").context(format!(
+            "Constructed response '{}' in chunk '{}' has no question",
+            id, chunk_slug
+        ))?;
+        let answer = get_attribute::<String>(component, "Answer").context(format!(
+            "Constructed response '{}' in chunk '{}' has no answer",
+            id, chunk_slug
+        ))?;
+        let difficulty = get_attribute::<String>(component, "Difficulty")
+            .map(|d| d.parse::<Difficulty>())
+            .transpose()
+            .context(format!(
+                "Constructed response '{}' in chunk '{}' has an invalid difficulty",
+                id, chunk_slug
+            ))?;
+
+        items.push(CriItem {
+            id,
+            question,
+            answer,
+            slug: chunk_slug.to_string(),
+            question_type: get_attribute::<String>(component, "Type").filter(|t| !t.is_empty()),
+            difficulty,
+            rubric: get_attribute::<String>(component, "Rubric").filter(|r| !r.is_empty()),
+            keywords: parse_list(component.get("Keywords")),
+        });
+    }
 
-    Some(CriItem {
-        slug: chunk_slug.to_string(),
-        question,
-        answer,
-    })
+    Ok(items)
 }
 
 /// Parses a video chunk
@@ -135,7 +185,8 @@ fn parse_video(chunk: &Value, page_title: &str) -> Result<ChunkData> {
         &title, page_title
     ))?;
 
-    let cri = parse_cri(chunk, &chunk_slug);
+    let cri = parse_cri(chunk, &chunk_slug)?;
+    let reveal = parse_reveal(chunk, &chunk_slug)?;
 
     let video_id = video_url
         .split("v=")
@@ -157,6 +208,79 @@ fn parse_video(chunk: &Value, page_title: &str) -> Result<ChunkData> {
         cri,
         show_header: true,
         chunk_type: ChunkType::Video,
+        reveal,
+        exercise: None,
+    })
+}
+
+/// Parses a code exercise chunk, its content is the prompt followed by an `i-exercise` element
+fn parse_exercise(chunk: &Value, page_title: &str) -> Result<ChunkData> {
+    let title = get_attribute::<String>(chunk, "Header").context(format!(
+        "Exercise chunk in page '{}' must set Header",
+        page_title
+    ))?;
+
+    let chunk_slug = get_attribute::<String>(chunk, "Slug").context(format!(
+        "Exercise chunk '{}' in page '{}' must set Slug",
+        &title, page_title
+    ))?;
+
+    let prompt = get_attribute::<String>(chunk, "Prompt").context(format!(
+        "Exercise chunk '{}' in page '{}' must set Prompt",
+        &title, page_title
+    ))?;
+
+    let language = get_attribute::<String>(chunk, "Language")
+        .map(|l| l.trim().to_lowercase())
+        .filter(|l| !l.is_empty())
+        .context(format!(
+            "Exercise chunk '{}' in page '{}' must set Language",
+            &title, page_title
+        ))?;
+    // the language names an editor mode, e.g. `c++`, `c#` or `objective-c`
+    if !language
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '+' | '#' | '-'))
+    {
+        anyhow::bail!(
+            "Exercise chunk '{}' in page '{}' has an invalid Language '{}', only a-z, 0-9, '+', '#' and '-' are allowed",
+            &title,
+            page_title,
+            language
+        );
+    }
+
+    let code = |name: &str| get_attribute::<String>(chunk, name).filter(|c| !c.trim().is_empty());
+    let exercise = Exercise {
+        slug: chunk_slug.clone(),
+        language,
+        prompt,
+        starter_code: code("StarterCode"),
+        tests: code("Tests"),
+        solution: code("Solution"),
+    };
+
+    let content = format!(
+        "{}\n\n<i-exercise chunk-slug=\"{}\" language=\"{}\">\n\n</i-exercise>\n\n",
+        exercise.prompt,
+        escape(&chunk_slug),
+        escape(&exercise.language)
+    );
+
+    let show_header = get_attribute::<bool>(chunk, "ShowHeader").unwrap_or(true);
+    let cri = parse_cri(chunk, &chunk_slug)?;
+    let reveal = parse_reveal(chunk, &chunk_slug)?;
+
+    Ok(ChunkData {
+        title,
+        slug: chunk_slug,
+        depth: 2,
+        content,
+        cri,
+        show_header,
+        chunk_type: ChunkType::Exercise,
+        reveal,
+        exercise: Some(exercise),
     })
 }
 
@@ -183,7 +307,8 @@ fn parse_regular_chunk(
     ))?;
 
     let show_header = get_attribute::<bool>(chunk, "ShowHeader").unwrap_or_default();
-    let cri = parse_cri(chunk, &chunk_slug);
+    let cri = parse_cri(chunk, &chunk_slug)?;
+    let reveal = parse_reveal(chunk, &chunk_slug)?;
 
     let header_level = get_attribute::<String>(chunk, "HeaderLevel");
     let depth = match header_level.as_deref() {
@@ -200,9 +325,21 @@ fn parse_regular_chunk(
         cri,
         show_header,
         chunk_type,
+        reveal,
+        exercise: None,
     })
 }
 
+/// Reveal mode from the optional `Reveal` field, standalone when unset
+fn parse_reveal(chunk: &Value, chunk_slug: &str) -> Result<RevealMode> {
+    match get_attribute::<String>(chunk, "Reveal").filter(|r| !r.is_empty()) {
+        Some(reveal) => reveal
+            .parse()
+            .context(format!("Chunk '{}' has an invalid reveal mode", chunk_slug)),
+        None => Ok(RevealMode::default()),
+    }
+}
+
 /// Unified quiz parsing function for all quiz types
 fn parse_quiz(page: &Value) -> Result<Option<Vec<QuizItem>>> {
     let questions = match page
@@ -214,10 +351,14 @@ fn parse_quiz(page: &Value) -> Result<Option<Vec<QuizItem>>> {
         None => return Ok(None),
     };
 
-    let quiz_items = questions
+    // a generated question component may hold several items
+    let quiz_items: Vec<QuizItem> = questions
         .iter()
-        .map(|q| parse_quiz_item(q))
-        .collect::<Result<Vec<_>>>()?;
+        .map(parse_quiz_item)
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect();
 
     if quiz_items.is_empty() {
         Ok(None)
@@ -226,19 +367,33 @@ fn parse_quiz(page: &Value) -> Result<Option<Vec<QuizItem>>> {
     }
 }
 
-/// Parse a single quiz item from different formats
-fn parse_quiz_item(question: &Value) -> Result<QuizItem> {
+/// Parse the quiz items of a question component from different formats
+fn parse_quiz_item(question: &Value) -> Result<Vec<QuizItem>> {
     let id = get_attribute::<String>(question, "id").context("Quiz question has no id")?;
+    let component = get_attribute::<String>(question, "__component");
 
+    // Strapi numbers every component type separately, so ids are prefixed by their component
     // Check if it's a multiple-choice question
-    if question.get("__component").and_then(|c| c.as_str())
-        == Some("quizzes.multiple-choice-question")
-    {
-        parse_multiple_choice_question(question, &id)
+    if component.as_deref() == Some("quizzes.multiple-choice-question") {
+        Ok(vec![parse_multiple_choice_question(
+            question,
+            &format!("mc-{}", id),
+        )?])
     }
     // Check if it's a generated question (YAML format)
     else if let Some(text) = question.get("GeneratedQuestion").and_then(|q| q.as_str()) {
-        parse_generated_question(text)
+        let id = format!("gen-{}", id);
+        let mut items = parse_generated_question(text)
+            .context(format!("Generated quiz question '{}' is invalid", id))?;
+        // items without an id of their own are numbered after the component id
+        let numbered = items.len() > 1;
+        for (index, item) in items.iter_mut().enumerate() {
+            item.id.get_or_insert_with(|| match numbered {
+                true => format!("{}-{}", id, index + 1),
+                false => id.clone(),
+            });
+        }
+        Ok(items)
     } else {
         Err(anyhow::anyhow!(
             "Quiz item is missing a valid '__component' or 'GeneratedQuestion' field"
@@ -261,12 +416,47 @@ fn parse_multiple_choice_question(question: &Value, id: &str) -> Result<QuizItem
         .map(|a| parse_quiz_answer(a, id))
         .collect::<Result<Vec<_>>>()?;
 
+    let question_type = match get_attribute::<String>(question, "Type") {
+        Some(t) => t
+            .parse()
+            .context(format!("Quiz question '{}' has an invalid type", id))?,
+        None => QuestionType::infer(&quiz_answers),
+    };
+
+    let difficulty = get_attribute::<String>(question, "Difficulty")
+        .map(|d| d.parse::<Difficulty>())
+        .transpose()
+        .context(format!("Quiz question '{}' has an invalid difficulty", id))?;
+
     Ok(QuizItem {
+        id: Some(id.to_string()),
+        question_type,
         question: question_text,
         answers: quiz_answers,
+        explanation: get_attribute::<String>(question, "Explanation").filter(|e| !e.is_empty()),
+        difficulty,
+        objectives: parse_list(question.get("Objectives")),
     })
 }
 
+/// Learning objectives or keywords, either a list or a comma-separated string
+fn parse_list(value: Option<&Value>) -> Vec<String> {
+    let items: Vec<String> = match value {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        Some(Value::String(s)) => s.split(',').map(str::to_string).collect(),
+        _ => Vec::new(),
+    };
+
+    items
+        .into_iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
 /// Parse a quiz answer item
 fn parse_quiz_answer(answer: &Value, question_id: &str) -> Result<QuizAnswerItem> {
     let answer_id = get_attribute::<String>(answer, "id").context(format!(
@@ -285,20 +475,69 @@ fn parse_quiz_answer(answer: &Value, question_id: &str) -> Result<QuizAnswerItem
     ))?;
 
     Ok(QuizAnswerItem {
+        id: Some(answer_id),
         answer: answer_text,
         correct,
+        feedback: get_attribute::<String>(answer, "Feedback").filter(|f| !f.is_empty()),
     })
 }
 
-/// Parse a generated question in YAML format
-fn parse_generated_question(yaml_text: &str) -> Result<QuizItem> {
-    let quiz_items: Vec<QuizItem> =
+/// A question in the generated-question YAML, the type is inferred when missing
+#[derive(Deserialize)]
+struct GeneratedQuestion {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default, rename = "type")]
+    question_type: Option<String>,
+    question: String,
+    answers: Vec<QuizAnswerItem>,
+    #[serde(default)]
+    explanation: Option<String>,
+    #[serde(default)]
+    difficulty: Option<String>,
+    #[serde(default)]
+    objectives: Vec<String>,
+}
+
+impl TryFrom<GeneratedQuestion> for QuizItem {
+    type Error = anyhow::Error;
+
+    fn try_from(generated: GeneratedQuestion) -> Result<Self> {
+        let question_type = match generated.question_type {
+            Some(t) => t.parse()?,
+            None => QuestionType::infer(&generated.answers),
+        };
+        let difficulty = generated.difficulty.map(|d| d.parse()).transpose()?;
+
+        Ok(QuizItem {
+            id: generated.id,
+            question_type,
+            question: generated.question,
+            answers: generated.answers,
+            explanation: generated.explanation,
+            difficulty,
+            objectives: generated.objectives,
+        })
+    }
+}
+
+/// Parse generated questions in YAML format, a list of one or more items
+fn parse_generated_question(yaml_text: &str) -> Result<Vec<QuizItem>> {
+    let quiz_items: Vec<GeneratedQuestion> =
         serde_yaml_ng::from_str(yaml_text).context("Quiz format is invalid")?;
 
+    if quiz_items.is_empty() {
+        anyhow::bail!("Generated question YAML contains no items");
+    }
+
     quiz_items
         .into_iter()
-        .next()
-        .ok_or_else(|| anyhow::anyhow!("Generated question YAML contains no items"))
+        .enumerate()
+        .map(|(index, item)| {
+            item.try_into()
+                .context(format!("Generated question {} is invalid", index + 1))
+        })
+        .collect()
 }
 
 /// Parse ClozeTest from the API response
@@ -314,9 +553,41 @@ fn parse_cloze_test(page: &Value) -> Result<Option<ClozeTest>> {
     Ok(Some(cloze_test))
 }
 
+/// Page description from Strapi, or the leading sentences of the first chunk with prose
+fn page_description(page: &Value, chunks: &[ChunkData]) -> Option<String> {
+    if let Some(description) = get_attribute::<String>(page, "Description") {
+        if !description.trim().is_empty() {
+            return Some(description.trim().to_string());
+        }
+    }
+
+    chunks
+        .iter()
+        .filter(|chunk| matches!(chunk.chunk_type, ChunkType::Regular | ChunkType::Plain))
+        .map(|chunk| plain_text(&chunk.content))
+        .find(|text| !text.is_empty())
+        .map(|text| lead_sentences(&text, DESCRIPTION_LENGTH))
+}
+
+/// Cover image URL, either a plain string or a Strapi media object
+fn cover_image(page: &Value) -> Option<String> {
+    let url = match page.get("CoverImage")? {
+        Value::String(url) => url.as_str(),
+        media => media.get("url")?.as_str()?,
+    };
+    Some(url.trim().to_string()).filter(|url| !url.is_empty())
+}
+
 /// Collects and processes pages from volume data
 pub fn collect_pages(resp: &VolumeData) -> Result<Vec<PageData>> {
-    resp.pages
+    let volume_settings = resp
+        .volume_config
+        .as_ref()
+        .map(|config| config.assignments)
+        .unwrap_or_default();
+
+    let mut pages = resp
+        .pages
         .iter()
         .enumerate()
         .map(|(index, page)| {
@@ -326,10 +597,19 @@ pub fn collect_pages(resp: &VolumeData) -> Result<Vec<PageData>> {
             let slug = get_attribute::<String>(page, "Slug")
                 .context(format!("Page '{}' must set slug", &title))?;
 
+            let assignment_settings = match page.get("AssignmentConfig").filter(|c| !c.is_null()) {
+                Some(config) => AssignmentSettings::parse(config).context(format!(
+                    "Page '{}' has an invalid AssignmentConfig",
+                    &title
+                ))?,
+                None => AssignmentSettings::default(),
+            }
+            .or(&volume_settings);
+
             let mut assignments = match get_attribute::<bool>(page, "HasSummary")
                 .context(format!("Page '{}' must set HasSummary", &title))?
             {
-                true => vec![String::from("summary")],
+                true => vec![Assignment::Summary(assignment_settings.summary)],
                 false => Vec::new(),
             };
 
@@ -352,7 +632,7 @@ pub fn collect_pages(resp: &VolumeData) -> Result<Vec<PageData>> {
                 parse_quiz(page).context(format!("Failed to parse quiz for page '{}'", &title))?;
 
             if quiz.is_some() {
-                assignments.push("quiz".to_string());
+                assignments.push(Assignment::Quiz(assignment_settings.quiz));
             }
 
             // Parse cloze test
@@ -360,7 +640,7 @@ pub fn collect_pages(resp: &VolumeData) -> Result<Vec<PageData>> {
                 .context(format!("Failed to parse ClozeTest for page '{}'", &title))?;
 
             if cloze_test.is_some() {
-                assignments.push("cloze".to_string());
+                assignments.push(Assignment::Cloze(assignment_settings.cloze));
             }
 
             // Parse content chunks
@@ -376,6 +656,12 @@ pub fn collect_pages(resp: &VolumeData) -> Result<Vec<PageData>> {
                 .map(|(index, chunk)| parse_chunk(chunk, index, &title))
                 .collect::<Result<Vec<_>>>()?;
 
+            if chunks.iter().any(|chunk| chunk.exercise.is_some()) {
+                assignments.push(Assignment::Exercises);
+            }
+
+            let description = page_description(page, &chunks);
+
             let order = get_attribute::<usize>(page, "Order")
                 .context(format!("Page '{}' must set Order", &title))?;
 
@@ -384,13 +670,22 @@ pub fn collect_pages(resp: &VolumeData) -> Result<Vec<PageData>> {
                 chunks,
                 slug,
                 parent,
+                description,
+                keywords: parse_list(page.get("Keywords")),
+                cover_image: cover_image(page),
                 order,
                 assignments,
+                assignment_settings,
                 quiz,
                 cloze_test,
+                free: false,
             })
         })
-        .collect()
+        .collect::<Result<Vec<_>>>()?;
+
+    resolve_free_pages(&resp.free_pages, &mut pages);
+
+    Ok(pages)
 }
 
 /// Parse a content chunk based on its type
@@ -400,63 +695,95 @@ fn parse_chunk(chunk: &Value, index: usize, page_title: &str) -> Result<ChunkDat
     let chunk_type = component.map_or(ChunkType::Regular, |c| match c.as_str() {
         "page.plain-chunk" => ChunkType::Plain,
         "page.video" => ChunkType::Video,
+        "page.exercise" => ChunkType::Exercise,
         _ => ChunkType::Regular,
     });
 
     // Parse based on chunk type
-    if matches!(chunk_type, ChunkType::Video) {
-        parse_video(chunk, page_title)
-    } else {
-        parse_regular_chunk(chunk, index, page_title, chunk_type)
+    match chunk_type {
+        ChunkType::Video => parse_video(chunk, page_title),
+        ChunkType::Exercise => parse_exercise(chunk, page_title),
+        _ => parse_regular_chunk(chunk, index, page_title, chunk_type),
     }
 }
 
 /// Serializes a page to a string with YAML frontmatter
-pub fn serialize_page(page: &PageData, next_slug: Option<&str>) -> Result<String> {
+pub fn serialize_page(page: &PageData, navigation: &PageNavigation) -> Result<String> {
     let mut fm: BTreeMap<&str, Frontmatter> = BTreeMap::new();
     fm.insert("title", Frontmatter::Title(page.title.as_str()));
     fm.insert("slug", Frontmatter::Slug(page.slug.as_str()));
-    fm.insert("next_slug", Frontmatter::NextSlug(next_slug));
+    fm.insert("prev_slug", Frontmatter::PrevSlug(navigation.prev_slug));
+    fm.insert("next_slug", Frontmatter::NextSlug(navigation.next_slug));
+    fm.insert("position", Frontmatter::Position(navigation.position.as_ref()));
     fm.insert("order", Frontmatter::Order(page.order));
+    fm.insert("reading_time", Frontmatter::ReadingTime(page_stats(page).reading_time));
+    fm.insert("free", Frontmatter::Free(page.free));
     fm.insert("assignments", Frontmatter::Assignments(&page.assignments));
+    fm.insert(
+        "assignment_config",
+        Frontmatter::AssignmentConfig(assignment_config(&page.assignments)),
+    );
     fm.insert("parent", Frontmatter::Parent(page.parent.as_ref()));
+    fm.insert("description", Frontmatter::Description(page.description.as_deref()));
+    fm.insert("keywords", Frontmatter::Keywords(&page.keywords));
+    fm.insert("cover_image", Frontmatter::CoverImage(page.cover_image.as_deref()));
     fm.insert("quiz", Frontmatter::Quiz(page.quiz.as_ref()));
     fm.insert("cloze_test", Frontmatter::ClozeTest(page.cloze_test.as_ref()));
 
     let mut cri = Vec::<&CriItem>::new();
+    let mut exercises = Vec::<&Exercise>::new();
     let mut chunks = Vec::<ChunkMeta>::new();
     let mut page_body = String::with_capacity(800 * page.chunks.len());
 
+    let mut slugger = page_slugger(page);
+
     // Process each chunk
     page.chunks.iter().for_each(|chunk| {
-        let mut chunk_headings = Vec::new();
-        let mut chunk_meta =
-            ChunkMeta::new(chunk.title.as_str(), chunk.slug.as_str(), &chunk.chunk_type);
+        let mut chunk_meta = ChunkMeta::new(
+            chunk.title.as_str(),
+            chunk.slug.as_str(),
+            &chunk.chunk_type,
+            chunk.reveal,
+        );
 
-        // Add to CRI if present
-        if let Some(ref item) = chunk.cri {
-            cri.push(item);
-        }
+        cri.extend(&chunk.cri);
+        exercises.extend(&chunk.exercise);
 
         // Generate page body
         let header_class = if chunk.show_header { "" } else { " .sr-only" };
+        // moved onto the wrapping section by the remark plugin
+        let reveal_attribute = match chunk.reveal {
+            RevealMode::Standalone => String::new(),
+            reveal => format!(" data-reveal=\"{}\"", reveal.as_str()),
+        };
 
-        let content = transform_headings(&chunk.content, &mut chunk_headings);
+        let (content, chunk_headings) = transform_headings(&chunk.content, &mut slugger);
 
         chunk_meta.add_headings(chunk_headings);
         chunks.push(chunk_meta);
 
         page_body.push_str(&format!(
-            "{} {} {{#{}{}}} \n\n{}\n\n",
+            "{} {} {{#{}{}{}}} \n\n{}\n\n",
             "#".repeat(chunk.depth),
             chunk.title,
             chunk.slug,
             header_class,
+            reveal_attribute,
             content
         ));
     });
 
-    fm.insert("cri", Frontmatter::CRI(&cri));
+    // the gapped passage follows the last chunk, the frontend keeps it out of the chunk's section
+    if let Some(test) = &page.cloze_test {
+        page_body.push_str(&format!(
+            "<i-cloze-test gaps=\"{}\">\n\n{}\n\n</i-cloze-test>\n",
+            test.gaps.len(),
+            render_cloze_markdown(test)
+        ));
+    }
+
+    fm.insert("cri", Frontmatter::Cri(&cri));
+    fm.insert("exercises", Frontmatter::Exercises(&exercises));
     fm.insert("chunks", Frontmatter::Chunks(chunks));
 
     Ok(format!(
@@ -468,3 +795,83 @@ pub fn serialize_page(page: &PageData, next_slug: Option<&str>) -> Result<String
         page_body
     ))
 }
+
+/// Serializes volume metadata to a YAML string, the content of `volume.yaml`
+pub fn serialize_volume(volume: &VolumeData, pages: &[PageData]) -> Result<String> {
+    let mut map = BTreeMap::<&str, VolumeFrontmatter>::new();
+    map.insert("title", VolumeFrontmatter::Title(volume.title.as_str()));
+    map.insert("slug", VolumeFrontmatter::Slug(volume.slug.as_str()));
+    map.insert(
+        "description",
+        VolumeFrontmatter::Description(volume.description.as_str()),
+    );
+
+    // Write the resolved page slugs, chapter and range entries are expanded
+    let mut free_pages: Vec<&PageData> = pages.iter().filter(|page| page.free).collect();
+    free_pages.sort_by_key(|page| page.order);
+    map.insert(
+        "free_pages",
+        VolumeFrontmatter::FreePages(free_pages.iter().map(|page| page.slug.as_str()).collect()),
+    );
+
+    map.insert(
+        "summary",
+        VolumeFrontmatter::Summary(volume.summary.as_deref()),
+    );
+
+    // Flatten VolumeConfig fields into top-level fields, reserved keys are rejected when parsing
+    let config_value = volume
+        .volume_config
+        .as_ref()
+        .map(serde_json::to_value)
+        .transpose()
+        .context("failed to serialize volume config")?;
+    if let Some(config_object) = config_value.as_ref().and_then(|c| c.as_object()) {
+        for (key, value) in config_object {
+            map.insert(key.as_str(), VolumeFrontmatter::VolumeConfigValue(value));
+        }
+    }
+
+    Ok(serde_yaml_ng::to_string(&map)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn exercise_chunk(slug: &str, language: &str) -> Value {
+        json!({
+            "__component": "page.exercise",
+            "Header": "Try it",
+            "Slug": slug,
+            "Prompt": "Write a function.",
+            "Language": language,
+        })
+    }
+
+    #[test]
+    fn parses_exercises() {
+        let chunk = parse_exercise(&exercise_chunk("try-it", " C++ "), "Page").unwrap();
+        let exercise = chunk.exercise.unwrap();
+        assert_eq!(exercise.language, "c++");
+        assert_eq!(exercise.prompt, "Write a function.");
+        assert!(chunk
+            .content
+            .contains("<i-exercise chunk-slug=\"try-it\" language=\"c++\">"));
+    }
+
+    #[test]
+    fn rejects_exercise_languages_outside_the_allowlist() {
+        for language in ["js\" onload=\"alert(1)", "python 3", "c<"] {
+            assert!(parse_exercise(&exercise_chunk("try-it", language), "Page").is_err());
+        }
+    }
+
+    #[test]
+    fn escapes_exercise_attributes() {
+        let chunk = parse_exercise(&exercise_chunk("a\"b", "rust"), "Page").unwrap();
+        assert!(chunk.content.contains("chunk-slug=\"a&quot;b\""));
+    }
+}
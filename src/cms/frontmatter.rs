@@ -1,38 +1,67 @@
+use std::collections::BTreeMap;
+
 use serde::Serialize;
 
-use super::{page::{QuizItem, ClozeTest}, ChunkType, CriItem, PageParent};
+use super::{assignment::Assignment, navigation::PagePosition, page::{QuizItem, ClozeTest}, ChunkType, CriItem, Exercise, PageParent, RevealMode};
 
 #[derive(Serialize, Debug)]
 #[serde(untagged)]
 pub enum Frontmatter<'a> {
     Title(&'a str),
     Slug(&'a str),
+    PrevSlug(Option<&'a str>),
     NextSlug(Option<&'a str>),
-    Assignments(&'a [String]),
+    Position(Option<&'a PagePosition<'a>>),
+    Assignments(&'a [Assignment]),
+    AssignmentConfig(BTreeMap<&'static str, serde_json::Value>),
     Parent(Option<&'a PageParent>),
+    Description(Option<&'a str>),
+    Keywords(&'a [String]),
+    CoverImage(Option<&'a str>),
     Order(usize),
+    ReadingTime(usize),
+    Free(bool),
     Chunks(Vec<ChunkMeta<'a>>),
-    CRI(&'a [&'a CriItem]),
+    Cri(&'a [&'a CriItem]),
+    Exercises(&'a [&'a Exercise]),
     Quiz(Option<&'a Vec<QuizItem>>),
     ClozeTest(Option<&'a ClozeTest>),
 }
 
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum VolumeFrontmatter<'a> {
+    Title(&'a str),
+    Slug(&'a str),
+    Description(&'a str),
+    FreePages(Vec<&'a str>),
+    Summary(Option<&'a str>),
+    VolumeConfigValue(&'a serde_json::Value),
+}
+
 #[derive(Serialize, Debug)]
 pub struct ChunkMeta<'a> {
     title: &'a str,
     slug: &'a str,
     #[serde(rename = "type")]
     chunk_type: &'a ChunkType,
+    reveal: RevealMode,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     headings: Vec<Heading>,
 }
 
 impl<'a> ChunkMeta<'a> {
-    pub fn new(title: &'a str, slug: &'a str, chunk_type: &'a ChunkType) -> Self {
+    pub fn new(
+        title: &'a str,
+        slug: &'a str,
+        chunk_type: &'a ChunkType,
+        reveal: RevealMode,
+    ) -> Self {
         Self {
             title,
             slug,
             chunk_type,
+            reveal,
             headings: vec![],
         }
     }
@@ -47,4 +76,7 @@ pub struct Heading {
     pub level: usize,
     pub slug: String,
     pub title: String,
+    /// nested headings with a higher level
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub children: Vec<Heading>,
 }
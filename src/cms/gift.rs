@@ -0,0 +1,537 @@
+use anyhow::{bail, Context, Result};
+
+use super::{
+    fetch::VolumeData,
+    page::{Difficulty, PageData, QuestionType, QuizAnswerItem, QuizItem},
+    tree::{TreeNode, VolumeTree},
+};
+
+/// Characters with a meaning in GIFT, escaped with a backslash in text
+const SPECIAL_CHARACTERS: &[char] = &['~', '=', '#', '{', '}', ':', '\\'];
+
+/// Questions that share a `$CATEGORY`
+#[derive(Debug)]
+pub struct GiftCategory {
+    /// category path without the `$course$` prefix, empty before the first `$CATEGORY`
+    pub path: Vec<String>,
+    pub items: Vec<QuizItem>,
+}
+
+/// Serializes every quiz of the volume to Moodle GIFT.
+///
+/// Each page gets a `$CATEGORY` of volume, chapter and page titles. Questions with several
+/// correct answers split the credit between them and give a full penalty for wrong ones.
+pub fn serialize_gift(volume: &VolumeData, pages: &[PageData]) -> String {
+    let tree = VolumeTree::build(pages);
+    let mut output = String::new();
+
+    let mut page_quiz = |chapter: Option<&str>, page: &PageData| {
+        let Some(quiz) = page.quiz.as_ref().filter(|quiz| !quiz.is_empty()) else {
+            return;
+        };
+
+        let path: Vec<String> = [
+            Some(volume.title.as_str()),
+            chapter,
+            Some(page.title.as_str()),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|name| name.replace('/', "//"))
+        .collect();
+        output.push_str(&format!("$CATEGORY: $course$/{}\n\n", path.join("/")));
+
+        for (index, item) in quiz.iter().enumerate() {
+            output.push_str(&gift_question(
+                &format!("{}-q{}", page.slug, index + 1),
+                item,
+            ));
+        }
+    };
+
+    for node in &tree.nodes {
+        match node {
+            TreeNode::Chapter(chapter) => {
+                for page in &chapter.pages {
+                    page_quiz(Some(chapter.title), page.page);
+                }
+            }
+            TreeNode::Page(page) => page_quiz(None, page.page),
+        }
+    }
+
+    output
+}
+
+/// Parses GIFT text into quiz items grouped by category.
+///
+/// Multiple choice, true/false and short answer questions fit the quiz model, other question
+/// types are reported as errors. Titles become question ids and general feedback the
+/// explanation, answer weights only keep their sign.
+pub fn parse_gift(text: &str) -> Result<Vec<GiftCategory>> {
+    let mut categories = vec![GiftCategory {
+        path: Vec::new(),
+        items: Vec::new(),
+    }];
+    let mut errors = Vec::new();
+
+    let mut question_count = 0;
+    for block in gift_blocks(text) {
+        if let Some(category) = block.strip_prefix("$CATEGORY:") {
+            categories.push(GiftCategory {
+                path: category_path(category.trim()),
+                items: Vec::new(),
+            });
+            continue;
+        }
+
+        question_count += 1;
+        match parse_gift_question(&block) {
+            Ok(item) => categories
+                .last_mut()
+                .expect("there is always a category")
+                .items
+                .push(item),
+            Err(err) => errors.push(format!("question {}: {:#}", question_count, err)),
+        }
+    }
+
+    if !errors.is_empty() {
+        bail!("Invalid GIFT questions:\n{}", errors.join("\n"));
+    }
+
+    categories.retain(|category| !category.items.is_empty());
+    Ok(categories)
+}
+
+fn gift_question(title: &str, item: &QuizItem) -> String {
+    let mut output = String::new();
+    if let Some(difficulty) = item.difficulty {
+        output.push_str(&format!("// difficulty: {}\n", difficulty_name(difficulty)));
+    }
+    if !item.objectives.is_empty() {
+        output.push_str(&format!("// objectives: {}\n", item.objectives.join(", ")));
+    }
+
+    let title = item.id.as_deref().unwrap_or(title);
+    let mut block = match true_false(item) {
+        Some((truth, wrong_feedback, right_feedback)) => {
+            let mut block = String::from(if truth { "T" } else { "F" });
+            if wrong_feedback.is_some() || right_feedback.is_some() {
+                block.push_str(&format!(
+                    "#{}#{}",
+                    escape(wrong_feedback.unwrap_or_default()),
+                    escape(right_feedback.unwrap_or_default())
+                ));
+            }
+            block
+        }
+        None => {
+            let correct = item.answers.iter().filter(|a| a.correct).count();
+            let wrong = item.answers.len() - correct;
+            let answers: String = item
+                .answers
+                .iter()
+                // wrong answers of a short answer question would be accepted too
+                .filter(|answer| item.question_type != QuestionType::ShortAnswer || answer.correct)
+                .map(|answer| {
+                    let prefix = match (item.question_type, correct, answer.correct) {
+                        (QuestionType::ShortAnswer, _, _) => "=".to_string(),
+                        (_, 1, true) => "=".to_string(),
+                        (_, 1, false) => "~".to_string(),
+                        (_, _, true) => format!("~%{}%", weight(100.0 / correct as f64)),
+                        (_, _, false) => format!("~%{}%", weight(-100.0 / wrong.max(1) as f64)),
+                    };
+                    let feedback = answer
+                        .feedback
+                        .as_deref()
+                        .map(|f| format!(" #{}", escape(f)))
+                        .unwrap_or_default();
+                    format!("\n\t{}{}{}", prefix, escape(&answer.answer), feedback)
+                })
+                .collect();
+            answers + "\n"
+        }
+    };
+    if let Some(explanation) = &item.explanation {
+        block.push_str(&format!("####{}", escape(explanation)));
+        if block.contains('\n') {
+            block.push('\n');
+        }
+    }
+
+    output.push_str(&format!(
+        "::{}::{} {{{}}}\n\n",
+        escape(title),
+        escape(&item.question),
+        block
+    ));
+    output
+}
+
+/// Truth value and wrong/right feedback when the question fits GIFT's true/false form
+fn true_false(item: &QuizItem) -> Option<(bool, Option<&str>, Option<&str>)> {
+    if item.question_type != QuestionType::TrueFalse || item.answers.len() != 2 {
+        return None;
+    }
+    let correct = item.answers.iter().find(|a| a.correct)?;
+    let wrong = item.answers.iter().find(|a| !a.correct)?;
+    let truth = match correct.answer.to_lowercase().as_str() {
+        "true" => true,
+        "false" => false,
+        _ => return None,
+    };
+    Some((
+        truth,
+        wrong.feedback.as_deref(),
+        correct.feedback.as_deref(),
+    ))
+}
+
+fn difficulty_name(difficulty: Difficulty) -> &'static str {
+    match difficulty {
+        Difficulty::Easy => "easy",
+        Difficulty::Medium => "medium",
+        Difficulty::Hard => "hard",
+    }
+}
+
+/// Moodle accepts up to five decimals in answer weights
+fn weight(value: f64) -> String {
+    let formatted = format!("{:.5}", value);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            c if SPECIAL_CHARACTERS.contains(&c) => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                chars.next();
+                unescaped.push('\n');
+            }
+            ('\\', Some(next)) if SPECIAL_CHARACTERS.contains(&next) => {
+                chars.next();
+                unescaped.push(next);
+            }
+            (c, _) => unescaped.push(c),
+        }
+    }
+    unescaped.trim().to_string()
+}
+
+/// Splits GIFT text into questions and `$CATEGORY` lines, dropping `//` comments
+/// other than the difficulty and objectives written by [`serialize_gift`]
+fn gift_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current = String::new();
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("//") && metadata_comment(trimmed).is_none() {
+            continue;
+        }
+        if trimmed.is_empty() || trimmed.starts_with("$CATEGORY:") {
+            if !current.trim().is_empty() {
+                blocks.push(current.trim().to_string());
+            }
+            current.clear();
+            if trimmed.starts_with("$CATEGORY:") {
+                blocks.push(trimmed.to_string());
+            }
+            continue;
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.trim().is_empty() {
+        blocks.push(current.trim().to_string());
+    }
+
+    blocks
+}
+
+/// Splits a category on single slashes, `//` is a literal slash
+fn category_path(category: &str) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut current = String::new();
+    let mut chars = category.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                chars.next();
+                current.push('/');
+            }
+            '/' => path.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    path.push(current);
+
+    path.into_iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| {
+            !name.is_empty() && name != "$course$" && name != "$system$" && name != "top"
+        })
+        .collect()
+}
+
+/// Byte index of the first unescaped occurrence of `target` in `text`
+fn find_unescaped(text: &str, target: char) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == target => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Key and value of a `// difficulty:` or `// objectives:` comment
+fn metadata_comment(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.strip_prefix("//")?.split_once(':')?;
+    let key = key.trim();
+    matches!(key, "difficulty" | "objectives").then_some((key, value.trim()))
+}
+
+fn parse_gift_question(block: &str) -> Result<QuizItem> {
+    let mut difficulty = None;
+    let mut objectives = Vec::new();
+    let mut lines = Vec::new();
+    for line in block.lines() {
+        match metadata_comment(line.trim()) {
+            Some(("difficulty", value)) => difficulty = Some(value.parse()?),
+            Some((_, value)) => objectives.extend(
+                value
+                    .split(',')
+                    .map(|o| o.trim().to_string())
+                    .filter(|o| !o.is_empty()),
+            ),
+            None => lines.push(line),
+        }
+    }
+    let block = lines.join("\n");
+    let mut rest = block.trim();
+
+    // optional ::title::, used as the question id
+    let mut id = None;
+    if let Some(after) = rest.strip_prefix("::") {
+        let end = after
+            .find("::")
+            .context("question title is not closed with '::'")?;
+        id = Some(unescape(&after[..end])).filter(|title| !title.is_empty());
+        rest = after[end + 2..].trim_start();
+    }
+
+    // optional text format marker such as [markdown]
+    if rest.starts_with('[') {
+        if let Some(end) = rest.find(']') {
+            rest = rest[end + 1..].trim_start();
+        }
+    }
+
+    let open = find_unescaped(rest, '{').context("question has no answer block")?;
+    let close = open
+        + 1
+        + find_unescaped(&rest[open + 1..], '}').context("answer block is not closed with '}'")?;
+
+    // text after the block makes a "missing word" question, the gap is shown as a blank
+    let before = rest[..open].trim();
+    let after = rest[close + 1..].trim();
+    let question = if after.is_empty() {
+        unescape(before)
+    } else {
+        format!("{} _____ {}", unescape(before), unescape(after))
+    };
+    if question.is_empty() {
+        bail!("question has no text");
+    }
+
+    let mut answer_block = rest[open + 1..close].trim();
+    let mut explanation = None;
+    if let Some(index) = answer_block.find("####") {
+        explanation = Some(unescape(&answer_block[index + 4..])).filter(|e| !e.is_empty());
+        answer_block = answer_block[..index].trim();
+    }
+
+    let (question_type, answers) = parse_gift_answers(answer_block)?;
+    Ok(QuizItem {
+        id,
+        question_type,
+        question,
+        answers,
+        explanation,
+        difficulty,
+        objectives,
+    })
+}
+
+fn parse_gift_answers(block: &str) -> Result<(QuestionType, Vec<QuizAnswerItem>)> {
+    if block.starts_with('#') {
+        bail!("numerical questions are not supported");
+    }
+
+    let (head, feedback) = match find_unescaped(block, '#') {
+        Some(index) => (block[..index].trim(), Some(&block[index + 1..])),
+        None => (block, None),
+    };
+    match head.to_ascii_uppercase().as_str() {
+        "T" | "TRUE" | "F" | "FALSE" => {
+            let truth = head.starts_with(['T', 't']);
+            // feedback for the wrong answer comes first
+            let (wrong_feedback, right_feedback) = match feedback {
+                Some(feedback) => match find_unescaped(feedback, '#') {
+                    Some(index) => (
+                        feedback[..index].to_string(),
+                        feedback[index + 1..].to_string(),
+                    ),
+                    None => (feedback.to_string(), String::new()),
+                },
+                None => (String::new(), String::new()),
+            };
+            let feedback_for = |correct: bool| {
+                Some(unescape(if correct {
+                    &right_feedback
+                } else {
+                    &wrong_feedback
+                }))
+                .filter(|f| !f.is_empty())
+            };
+            let answers = [("True", truth), ("False", !truth)]
+                .into_iter()
+                .map(|(answer, correct)| QuizAnswerItem {
+                    id: None,
+                    answer: answer.to_string(),
+                    correct,
+                    feedback: feedback_for(correct),
+                })
+                .collect();
+            return Ok((QuestionType::TrueFalse, answers));
+        }
+        "" => bail!("essay questions are not supported"),
+        _ => {}
+    }
+
+    // split on unescaped answer markers
+    let mut answers = Vec::new();
+    let mut start: Option<(usize, char)> = None;
+    let mut escaped = false;
+    for (index, c) in block.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '=' | '~' => {
+                if let Some((begin, marker)) = start {
+                    answers.push((marker, &block[begin..index]));
+                }
+                start = Some((index + 1, c));
+            }
+            _ => {}
+        }
+    }
+    match start {
+        Some((begin, marker)) => answers.push((marker, &block[begin..])),
+        None => bail!("answer block has no '=' or '~' answers"),
+    }
+
+    if answers.iter().any(|(_, text)| text.contains("->")) {
+        bail!("matching questions are not supported");
+    }
+    // only accepted responses, no choices to pick from
+    let short_answer = answers.iter().all(|(marker, _)| *marker == '=');
+
+    let answers = answers
+        .into_iter()
+        .map(|(marker, text)| {
+            let (text, feedback) = match find_unescaped(text, '#') {
+                Some(index) => (&text[..index], Some(unescape(&text[index + 1..]))),
+                None => (text, None),
+            };
+            let text = text.trim_start();
+            let (correct, text) = match text.strip_prefix('%') {
+                Some(weighted) => {
+                    let end = weighted
+                        .find('%')
+                        .context("answer weight is not closed with '%'")?;
+                    let weight: f64 = weighted[..end]
+                        .trim()
+                        .parse()
+                        .context(format!("invalid answer weight '{}'", &weighted[..end]))?;
+                    (weight > 0.0, &weighted[end + 1..])
+                }
+                None => (marker == '=', text),
+            };
+
+            let answer = unescape(text);
+            if answer.is_empty() {
+                bail!("answer has no text");
+            }
+            Ok(QuizAnswerItem {
+                id: None,
+                answer,
+                correct,
+                feedback: feedback.filter(|f| !f.is_empty()),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let question_type = if short_answer {
+        QuestionType::ShortAnswer
+    } else {
+        QuestionType::infer(&answers)
+    };
+    Ok((question_type, answers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_only_correct_short_answers() {
+        let item = QuizItem {
+            id: Some("gen-3".to_string()),
+            question_type: QuestionType::ShortAnswer,
+            question: "Capital of France?".to_string(),
+            answers: [("Paris", true), ("London", false)]
+                .into_iter()
+                .map(|(answer, correct)| QuizAnswerItem {
+                    id: None,
+                    answer: answer.to_string(),
+                    correct,
+                    feedback: None,
+                })
+                .collect(),
+            explanation: None,
+            difficulty: None,
+            objectives: vec![],
+        };
+        assert_eq!(
+            gift_question("Question", &item),
+            "::gen-3::Capital of France? {\n\t=Paris\n}\n\n"
+        );
+    }
+}
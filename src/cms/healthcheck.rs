@@ -28,7 +28,7 @@ pub struct PageHealthCheck {
 #[derive(Error, Debug)]
 pub enum RequestError {
     #[error("HTTP request failed: {0}")]
-    Http(#[from] ureq::Error),
+    Http(#[from] Box<ureq::Error>),
 
     #[error("Failed to read response body: {0}")]
     IO(#[from] std::io::Error),
@@ -48,7 +48,7 @@ pub fn get_embedding_slugs(supabase_url: &str, api_key: &str, volume_slug: &str)
         .call()
         .map_err(|e| match e {
             ureq::Error::Status(code, _) => RequestError::SupabaseError { status: code },
-            other => RequestError::Http(other),
+            other => RequestError::Http(Box::new(other)),
         })
         .context("Connecting to Supabase API")?;
 
@@ -133,7 +133,7 @@ pub fn save_health_check_to_supabase(
         .send_json(&payload)
         .map_err(|e| match e {
             ureq::Error::Status(code, _) => RequestError::SupabaseError { status: code },
-            other => RequestError::Http(other),
+            other => RequestError::Http(Box::new(other)),
         })
         .context("Failed to save health check to Supabase log table")?;
 
@@ -0,0 +1,368 @@
+use pulldown_cmark::{html, Options, Parser};
+use regex::{Captures, Regex};
+
+use super::{
+    fetch::VolumeData,
+    markdown::{page_slugger, transform_headings},
+    page::PageData,
+};
+
+/// Named entities that may appear in raw HTML, XHTML only knows the XML ones
+const ENTITIES: &[(&str, u32)] = &[
+    ("nbsp", 160),
+    ("copy", 169),
+    ("reg", 174),
+    ("deg", 176),
+    ("times", 215),
+    ("ndash", 8211),
+    ("mdash", 8212),
+    ("lsquo", 8216),
+    ("rsquo", 8217),
+    ("ldquo", 8220),
+    ("rdquo", 8221),
+    ("hellip", 8230),
+    ("rarr", 8594),
+];
+
+/// Elements without content, written self-closed in XHTML
+const VOID_ELEMENTS: &[&str] = &["area", "br", "col", "hr", "img", "source", "track", "wbr"];
+
+/// A character reference XML knows, named ones other than these are replaced beforehand
+const REFERENCE: &str = r"^&(?:#[0-9]+|#x[0-9A-Fa-f]+|amp|lt|gt|quot|apos);";
+
+/// Stylesheet shared by the offline formats
+pub(crate) const STYLESHEET: &str = r#"body { font-family: serif; line-height: 1.5; }
+h1, h2, h3, h4, h5, h6 { font-family: sans-serif; }
+.video, .image-link { font-style: italic; }
+.review { border-top: 1px solid #888; margin-top: 2em; }
+.answers { font-size: 0.9em; }
+"#;
+
+/// Language used when the volume config doesn't set one
+const DEFAULT_LANGUAGE: &str = "en";
+
+/// Language tag of the volume
+pub(crate) fn volume_language(volume: &VolumeData) -> &str {
+    volume
+        .volume_config
+        .as_ref()
+        .and_then(|config| config.language.as_deref())
+        .unwrap_or(DEFAULT_LANGUAGE)
+}
+
+/// Escapes text for use in XML content and attribute values
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Renders chunk markdown to well-formed XHTML for offline formats.
+///
+/// `i-*` components are replaced by plain elements. Images aren't packaged, so remote ones
+/// become links and relative ones their alt text, only `data:` images are kept.
+pub(crate) fn render_xhtml(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_HEADING_ATTRIBUTES;
+    let mut output = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut output, Parser::new_ext(markdown, options));
+
+    let output = replace_components(&output);
+    let output = replace_images(&output);
+    let output = replace_entities(&output);
+    well_formed(&output)
+}
+
+/// Renders every chunk of a page as a `<section>` whose id is the chunk slug
+pub(crate) fn render_chunks(page: &PageData) -> String {
+    let mut body = String::new();
+    let mut slugger = page_slugger(page);
+    for chunk in &page.chunks {
+        let (content, _) = transform_headings(&chunk.content, &mut slugger);
+        body.push_str(&format!(
+            "  <section id=\"{}\" data-reveal=\"{}\">\n",
+            escape(&chunk.slug),
+            chunk.reveal.as_str()
+        ));
+        if chunk.show_header {
+            body.push_str(&format!("    <h2>{}</h2>\n", escape(&chunk.title)));
+        }
+        body.push_str(&render_xhtml(&content));
+        body.push_str("  </section>\n");
+    }
+    body
+}
+
+/// Wraps a rendered body in a standalone XHTML document
+pub(crate) fn xhtml_document(title: &str, language: &str, stylesheet: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" lang="{lang}" xml:lang="{lang}">
+<head>
+  <title>{title}</title>
+  <link rel="stylesheet" type="text/css" href="{stylesheet}"/>
+</head>
+<body>
+{body}</body>
+</html>
+"#,
+        lang = escape(language),
+        title = escape(title),
+        stylesheet = escape(stylesheet),
+    )
+}
+
+/// Extracts an attribute value from the attribute part of a tag, props may use `{}` values
+pub(crate) fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let regex = Regex::new(&format!(
+        r#"(?:^|\s){}\s*=\s*(?:"([^"]*)"|'([^']*)'|\{{([^}}]*)\}}|([^\s"'>/]+))"#,
+        regex::escape(name)
+    ))
+    .unwrap();
+    let caps = regex.captures(attributes)?;
+    (1..=4).find_map(|i| caps.get(i)).map(|m| m.as_str())
+}
+
+fn replace_components(html: &str) -> String {
+    let opening = Regex::new(r"<(i-[a-z0-9-]+)((?:\s[^>]*?)?)\s*(/?)>").unwrap();
+    let closing = Regex::new(r"</(i-[a-z0-9-]+)\s*>").unwrap();
+
+    let html = opening.replace_all(html, |caps: &Captures| {
+        let name = &caps[1];
+        let attributes = &caps[2];
+        let self_closing = !caps[3].is_empty();
+
+        let element = match name {
+            "i-youtube" => {
+                let video_id = attribute(attributes, "videoid").unwrap_or_default();
+                format!(
+                    "<p class=\"video\"><a href=\"https://www.youtube.com/watch?v={}\">Watch the video on YouTube</a></p>",
+                    escape(video_id)
+                )
+            }
+            "i-image" => format!(
+                "<figure class=\"image\">{}",
+                image_link(
+                    attribute(attributes, "src").unwrap_or_default(),
+                    attribute(attributes, "alt").unwrap_or_default()
+                )
+            ),
+            _ => format!("<div class=\"{}\">", name),
+        };
+
+        match (name, self_closing) {
+            ("i-youtube", _) => element,
+            ("i-image", true) => format!("{}</figure>", element),
+            (_, true) => format!("{}</div>", element),
+            _ => element,
+        }
+    });
+
+    closing
+        .replace_all(&html, |caps: &Captures| match &caps[1] {
+            "i-youtube" => String::new(),
+            "i-image" => "</figure>".to_string(),
+            _ => "</div>".to_string(),
+        })
+        .to_string()
+}
+
+fn replace_images(html: &str) -> String {
+    let image = Regex::new(r"<img\b([^>]*?)/?>").unwrap();
+    image
+        .replace_all(html, |caps: &Captures| {
+            let src = attribute(&caps[1], "src").unwrap_or_default();
+            if src.starts_with("data:") {
+                caps[0].to_string()
+            } else {
+                image_link(src, attribute(&caps[1], "alt").unwrap_or_default())
+            }
+        })
+        .to_string()
+}
+
+/// Links a remote image, a relative one would point outside the package so only its label is kept
+fn image_link(src: &str, alt: &str) -> String {
+    let label = escape_keeping_references(if alt.is_empty() { "Image" } else { alt });
+    if src.starts_with("http://") || src.starts_with("https://") {
+        format!(
+            "<a class=\"image-link\" href=\"{}\">{}</a>",
+            escape_keeping_references(src),
+            label
+        )
+    } else {
+        format!("<span class=\"image-link\">{}</span>", label)
+    }
+}
+
+/// Escapes text that may already contain character references, like values taken from
+/// rendered HTML, without escaping the references twice
+fn escape_keeping_references(text: &str) -> String {
+    let reference = Regex::new(REFERENCE).unwrap();
+    let mut output = String::with_capacity(text.len());
+    for (index, c) in text.char_indices() {
+        match c {
+            '&' if reference.is_match(&text[index..]) => output.push('&'),
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+/// Makes rendered HTML well-formed XML. The markdown renderer passes raw HTML through as is,
+/// so stray `<` and `&` are escaped, attributes quoted, void elements self-closed, unmatched
+/// end tags dropped and elements left open closed.
+fn well_formed(html: &str) -> String {
+    let tag = Regex::new(
+        r#"^<(/?)([A-Za-z][A-Za-z0-9-]*)((?:\s+[^\s"'<>/=]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'=<>`]+))?)*)\s*(/?)>"#,
+    )
+    .unwrap();
+    let attribute =
+        Regex::new(r#"([^\s"'<>/=]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#).unwrap();
+    let attribute_name = Regex::new(r"^[A-Za-z_:][A-Za-z0-9_.:-]*$").unwrap();
+    let reference = Regex::new(REFERENCE).unwrap();
+
+    let mut output = String::with_capacity(html.len());
+    let mut open: Vec<String> = Vec::new();
+    let mut index = 0;
+
+    while let Some(found) = html[index..].find(['<', '&']) {
+        let start = index + found;
+        output.push_str(&html[index..start]);
+        let rest = &html[start..];
+
+        if rest.starts_with('&') {
+            output.push_str(if reference.is_match(rest) {
+                "&"
+            } else {
+                "&amp;"
+            });
+            index = start + 1;
+            continue;
+        }
+        if let Some(end) = rest
+            .strip_prefix("<!--")
+            .and_then(|comment| comment.find("-->"))
+        {
+            output.push_str(&rest[..end + 7]);
+            index = start + end + 7;
+            continue;
+        }
+        let Some(caps) = tag.captures(rest) else {
+            output.push_str("&lt;");
+            index = start + 1;
+            continue;
+        };
+        index = start + caps[0].len();
+
+        let name = caps[2].to_lowercase();
+        if !caps[1].is_empty() {
+            if let Some(position) = open.iter().rposition(|element| *element == name) {
+                for element in open.drain(position..).rev() {
+                    output.push_str(&format!("</{}>", element));
+                }
+            }
+            continue;
+        }
+
+        output.push('<');
+        output.push_str(&name);
+        let mut names = Vec::new();
+        for attr in attribute.captures_iter(&caps[3]) {
+            let attr_name = attr[1].to_lowercase();
+            if !attribute_name.is_match(&attr_name) || names.contains(&attr_name) {
+                continue;
+            }
+            let value = (2..=4)
+                .find_map(|i| attr.get(i))
+                .map_or(attr_name.as_str(), |m| m.as_str());
+            output.push_str(&format!(
+                " {}=\"{}\"",
+                attr_name,
+                escape_keeping_references(value)
+            ));
+            names.push(attr_name);
+        }
+
+        if VOID_ELEMENTS.contains(&name.as_str()) || !caps[4].is_empty() {
+            output.push_str(" />");
+        } else {
+            output.push('>');
+            open.push(name);
+        }
+    }
+
+    output.push_str(&html[index..]);
+    for element in open.into_iter().rev() {
+        output.push_str(&format!("</{}>", element));
+    }
+    output
+}
+
+fn replace_entities(html: &str) -> String {
+    let entity = Regex::new(r"&([A-Za-z][A-Za-z0-9]*);").unwrap();
+    entity
+        .replace_all(html, |caps: &Captures| match &caps[1] {
+            "amp" | "lt" | "gt" | "quot" | "apos" => caps[0].to_string(),
+            name => match ENTITIES.iter().find(|(n, _)| *n == name) {
+                Some((_, code)) => format!("&#{};", code),
+                None => format!("&amp;{};", name),
+            },
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_image_links() {
+        let xhtml = render_xhtml(r#"<i-image src="https://a.com/x.png?a=1&b=2" alt='A & "c"' />"#);
+        assert!(xhtml.contains(
+            r#"<a class="image-link" href="https://a.com/x.png?a=1&amp;b=2">A &amp; &quot;c&quot;</a>"#
+        ));
+    }
+
+    #[test]
+    fn repairs_raw_html() {
+        assert_eq!(
+            render_xhtml("<div>\n\nA & B <span class=x>open\n\n</div>\n"),
+            "<div>\n<p>A &amp; B <span class=\"x\">open</span></p>\n</div>\n"
+        );
+        assert_eq!(
+            render_xhtml("<p title='a & b'>x</em> <br> 1 < 2 <details open></p>"),
+            "<p title=\"a &amp; b\">x <br /> 1 &lt; 2 <details open=\"open\"></details></p>"
+        );
+    }
+
+    #[test]
+    fn keeps_well_formed_html() {
+        let xhtml = render_xhtml("Text &mdash; *em*\n\n<!-- note -->\n\n![a](data:image/png,x)\n");
+        assert_eq!(
+            xhtml,
+            "<p>Text — <em>em</em></p>\n<!-- note -->\n<p><img src=\"data:image/png,x\" alt=\"a\" /></p>\n"
+        );
+    }
+
+    #[test]
+    fn replaces_relative_images_by_their_label() {
+        assert_eq!(
+            render_xhtml("![A chart](images/chart.png)\n"),
+            "<p><span class=\"image-link\">A chart</span></p>\n"
+        );
+        assert_eq!(
+            render_xhtml("<i-image src=\"/x.png\" />"),
+            "<figure class=\"image\"><span class=\"image-link\">Image</span></figure>"
+        );
+    }
+}
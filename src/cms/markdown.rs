@@ -0,0 +1,378 @@
+use github_slugger::Slugger;
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::Regex;
+
+use super::{frontmatter::Heading, page::PageData};
+
+/// Chunk content sits below the h2 chunk heading
+const MIN_HEADING_LEVEL: usize = 3;
+const MAX_HEADING_LEVEL: usize = 6;
+
+/// A slice of chunk markdown, either prose or a fenced code block
+#[derive(Debug)]
+pub(crate) enum Segment<'a> {
+    /// prose, which may still contain inline code spans
+    Text(&'a str),
+    /// fenced code block, including the fences
+    Code(&'a str),
+}
+
+/// Splits markdown into prose and fenced code blocks, concatenating them yields the input again
+pub(crate) fn split_fences(content: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+
+    let mut fence: Option<(char, usize)> = None;
+    let mut block_start = 0;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        match fence {
+            None => {
+                if let Some(open) = code_fence(line) {
+                    if line_start > block_start {
+                        segments.push(Segment::Text(&content[block_start..line_start]));
+                    }
+                    fence = Some(open);
+                    block_start = line_start;
+                }
+            }
+            Some((marker, len)) => {
+                if is_closing_fence(line, marker, len) {
+                    segments.push(Segment::Code(&content[block_start..offset]));
+                    fence = None;
+                    block_start = offset;
+                }
+            }
+        }
+    }
+
+    // an unterminated fence runs until the end of the chunk
+    if fence.is_some() {
+        segments.push(Segment::Code(&content[block_start..]));
+    } else if block_start < content.len() {
+        segments.push(Segment::Text(&content[block_start..]));
+    }
+
+    segments
+}
+
+/// A heading found outside code blocks
+struct HeadingLine<'a> {
+    /// index of the first line
+    line: usize,
+    /// number of lines, setext headings span two
+    span: usize,
+    level: usize,
+    text: &'a str,
+}
+
+/// A slugger for the headings of a page. It is seeded with the chunk slugs, so a heading id that
+/// repeats across chunks or matches a chunk anchor gets a numeric suffix.
+pub(crate) fn page_slugger(page: &PageData) -> Slugger {
+    let mut slugger = Slugger::default();
+    for chunk in &page.chunks {
+        slugger.slug(&chunk.slug);
+    }
+    slugger
+}
+
+/// Normalizes chunk headings to levels 3-6 and gives every heading an id from the page's slugger.
+/// Returns the transformed content and the nested heading tree.
+pub(crate) fn transform_headings(content: &str, slugger: &mut Slugger) -> (String, Vec<Heading>) {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let found = find_headings(&lines);
+
+    // shift all levels so that the outermost heading becomes an h3, keeping the hierarchy
+    let shift = found
+        .iter()
+        .map(|h| h.level)
+        .min()
+        .map_or(0, |min| MIN_HEADING_LEVEL.saturating_sub(min));
+
+    let mut headings = Vec::with_capacity(found.len());
+    let mut output = String::with_capacity(content.len());
+    let mut found = found.into_iter().peekable();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let Some(heading) = found.next_if(|h| h.line == index) else {
+            output.push_str(lines[index]);
+            index += 1;
+            continue;
+        };
+
+        let level = (heading.level + shift).min(MAX_HEADING_LEVEL);
+        let (title, id, classes) = parse_heading_attributes(heading.text);
+        let id = id.map_or_else(|| slugger.slug(title), str::to_string);
+        let last_line = lines[index + heading.span - 1];
+        let line_ending = &last_line[last_line.trim_end_matches(['\r', '\n']).len()..];
+
+        output.push_str(&format!(
+            "{} {} {{#{}{}}}{}",
+            "#".repeat(level),
+            title,
+            id,
+            classes,
+            line_ending
+        ));
+        headings.push(Heading {
+            level,
+            slug: id,
+            title: title.to_string(),
+            children: vec![],
+        });
+        index += heading.span;
+    }
+
+    (output, nest_headings(headings))
+}
+
+/// Prose of a markdown document, one paragraph per line.
+/// Headings, code, HTML and component tags are left out.
+pub(crate) fn plain_text(content: &str) -> String {
+    let mut paragraphs = Vec::new();
+    let mut current = String::new();
+    // depth of elements whose text is skipped
+    let mut skipped: usize = 0;
+
+    for event in Parser::new(content) {
+        match event {
+            Event::Start(Tag::Heading(..) | Tag::CodeBlock(_)) => skipped += 1,
+            Event::End(Tag::Heading(..) | Tag::CodeBlock(_)) => skipped = skipped.saturating_sub(1),
+            Event::End(Tag::Paragraph | Tag::Item) => {
+                if !current.trim().is_empty() {
+                    paragraphs.push(current.trim().to_string());
+                }
+                current.clear();
+            }
+            Event::Text(text) if skipped == 0 => current.push_str(&text),
+            Event::SoftBreak | Event::HardBreak if skipped == 0 => current.push(' '),
+            _ => {}
+        }
+    }
+    if !current.trim().is_empty() {
+        paragraphs.push(current.trim().to_string());
+    }
+
+    paragraphs.join("\n")
+}
+
+/// Flattens a heading tree in document order
+pub(crate) fn flatten_headings(headings: &[Heading]) -> Vec<&Heading> {
+    headings
+        .iter()
+        .flat_map(|h| std::iter::once(h).chain(flatten_headings(&h.children)))
+        .collect()
+}
+
+/// Finds ATX and setext headings, skipping fenced code blocks
+fn find_headings<'a>(lines: &[&'a str]) -> Vec<HeadingLine<'a>> {
+    let atx = Regex::new(r"^ {0,3}(#{1,6})[ \t]+(.+?)(?:[ \t]+#+)?[ \t]*$").unwrap();
+    let setext_underline = Regex::new(r"^ {0,3}(=+|-+)[ \t]*$").unwrap();
+    let block_start = Regex::new(r"^\s*([-*+>|<#]|\d+[.)])").unwrap();
+
+    let mut headings = Vec::new();
+    let mut fence: Option<(char, usize)> = None;
+    let mut previous_blank = true;
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index];
+        let text = line.trim_end_matches(['\r', '\n']);
+
+        if let Some((marker, len)) = fence {
+            if is_closing_fence(text, marker, len) {
+                fence = None;
+                previous_blank = true;
+            }
+            index += 1;
+            continue;
+        }
+
+        if let Some(open) = code_fence(text) {
+            fence = Some(open);
+            index += 1;
+            continue;
+        }
+
+        if let Some(caps) = atx.captures(text) {
+            headings.push(HeadingLine {
+                line: index,
+                span: 1,
+                level: caps[1].len(),
+                text: caps.get(2).unwrap().as_str(),
+            });
+            previous_blank = false;
+            index += 1;
+            continue;
+        }
+
+        // only single-line paragraphs are treated as setext headings
+        let underline = lines
+            .get(index + 1)
+            .and_then(|next| setext_underline.captures(next.trim_end_matches(['\r', '\n'])));
+        if let Some(underline) = underline {
+            if previous_blank && !text.trim().is_empty() && !block_start.is_match(text) {
+                headings.push(HeadingLine {
+                    line: index,
+                    span: 2,
+                    level: if underline[1].starts_with('=') { 1 } else { 2 },
+                    text: text.trim(),
+                });
+                previous_blank = false;
+                index += 2;
+                continue;
+            }
+        }
+
+        previous_blank = text.trim().is_empty();
+        index += 1;
+    }
+
+    headings
+}
+
+/// Splits a trailing `{#id .class key=value}` block off a heading, returns the title, the id and
+/// other attributes. Braces holding anything else, like `## Using {placeholders}`, are title text.
+fn parse_heading_attributes(text: &str) -> (&str, Option<&str>, String) {
+    let block = Regex::new(r"^(.*?)\s*\{([^}]*)\}$").unwrap();
+    let attribute =
+        Regex::new(r#"^(?:#[\w-]+|\.[\w-]+|[A-Za-z_][\w-]*=(?:"[^"]*"|'[^']*'|[^\s"']+))"#)
+            .unwrap();
+    let Some(caps) = block.captures(text) else {
+        return (text, None, String::new());
+    };
+
+    let mut tokens = Vec::new();
+    let mut rest = caps.get(2).unwrap().as_str().trim_start();
+    while !rest.is_empty() {
+        let Some(token) = attribute.find(rest) else {
+            return (text, None, String::new());
+        };
+        let after = &rest[token.end()..];
+        if !after.is_empty() && !after.starts_with(char::is_whitespace) {
+            return (text, None, String::new());
+        }
+        tokens.push(token.as_str());
+        rest = after.trim_start();
+    }
+    if tokens.is_empty() {
+        return (text, None, String::new());
+    }
+
+    let mut id = None;
+    let mut classes = String::new();
+    for token in tokens {
+        match token.strip_prefix('#') {
+            Some(value) if id.is_none() => id = Some(value),
+            _ => {
+                classes.push(' ');
+                classes.push_str(token);
+            }
+        }
+    }
+
+    (caps.get(1).unwrap().as_str(), id, classes)
+}
+
+/// Nests each heading under the closest preceding heading with a lower level
+fn nest_headings(flat: Vec<Heading>) -> Vec<Heading> {
+    let mut roots = Vec::new();
+    for heading in flat {
+        insert_heading(&mut roots, heading);
+    }
+    roots
+}
+
+fn insert_heading(siblings: &mut Vec<Heading>, heading: Heading) {
+    match siblings.last_mut() {
+        Some(last) if heading.level > last.level => insert_heading(&mut last.children, heading),
+        _ => siblings.push(heading),
+    }
+}
+
+/// Returns the fence character and length if the line opens a fenced code block
+fn code_fence(line: &str) -> Option<(char, usize)> {
+    let trimmed = strip_indent(line)?;
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.chars().take_while(|c| *c == marker).count();
+
+    // backtick fences cannot have backticks in their info string
+    if len < 3 || (marker == '`' && trimmed[len..].contains('`')) {
+        return None;
+    }
+
+    Some((marker, len))
+}
+
+/// Checks whether the line closes a fence opened with `marker` repeated `len` times
+fn is_closing_fence(line: &str, marker: char, len: usize) -> bool {
+    let Some(trimmed) = strip_indent(line) else {
+        return false;
+    };
+    let run = trimmed.chars().take_while(|c| *c == marker).count();
+    run >= len && trimmed[run * marker.len_utf8()..].trim().is_empty()
+}
+
+/// Strips up to three spaces of indentation, more than that makes an indented code block
+fn strip_indent(line: &str) -> Option<&str> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    (indent <= 3).then(|| &line[indent..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_attribute_blocks() {
+        assert_eq!(
+            parse_heading_attributes("Intro {#start .lead data-x=1}"),
+            ("Intro", Some("start"), " .lead data-x=1".to_string())
+        );
+        assert_eq!(
+            parse_heading_attributes(r#"Intro {.note title="A note"}"#),
+            ("Intro", None, r#" .note title="A note""#.to_string())
+        );
+    }
+
+    #[test]
+    fn keeps_braces_that_are_not_attributes() {
+        for text in [
+            "Using {placeholders}",
+            "Set notation {x}",
+            "Objects {a: 1}",
+            "Empty {}",
+            "Mixed {#id plain}",
+        ] {
+            assert_eq!(parse_heading_attributes(text), (text, None, String::new()));
+        }
+    }
+
+    #[test]
+    fn transforms_headings_with_braces_in_the_title() {
+        let (content, headings) = transform_headings(
+            "## Using {placeholders}\n\n### Set {#set .math}\n",
+            &mut Slugger::default(),
+        );
+        assert_eq!(
+            content,
+            "### Using {placeholders} {#using-placeholders}\n\n#### Set {#set .math}\n"
+        );
+        assert_eq!(headings[0].title, "Using {placeholders}");
+        assert_eq!(headings[0].children[0].slug, "set");
+    }
+
+    #[test]
+    fn suffixes_heading_ids_repeated_across_chunks() {
+        let mut slugger = Slugger::default();
+        slugger.slug("summary");
+        let (first, _) = transform_headings("### Summary\n", &mut slugger);
+        let (second, _) = transform_headings("### Summary\n", &mut slugger);
+        assert_eq!(first, "### Summary {#summary-1}\n");
+        assert_eq!(second, "### Summary {#summary-2}\n");
+    }
+}
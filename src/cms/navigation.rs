@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::{tree::VolumeTree, PageData};
+
+/// Decides which pages take part in prev/next navigation
+#[derive(Debug, Clone, Copy, Default)]
+pub enum NavigationRule {
+    /// every page is navigable
+    #[default]
+    IncludeAll,
+    /// pages without chunks are skipped, e.g. chapter landing pages
+    SkipEmpty,
+}
+
+impl NavigationRule {
+    pub fn includes(&self, page: &PageData) -> bool {
+        match self {
+            NavigationRule::IncludeAll => true,
+            NavigationRule::SkipEmpty => !page.chunks.is_empty(),
+        }
+    }
+}
+
+/// Navigation frontmatter of a single page
+#[derive(Debug, Default, Serialize)]
+pub struct PageNavigation<'a> {
+    pub prev_slug: Option<&'a str>,
+    pub next_slug: Option<&'a str>,
+    /// position among navigable pages, `None` for skipped pages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<PagePosition<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PagePosition<'a> {
+    /// zero-based index among navigable pages of the volume
+    pub index: usize,
+    pub total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chapter: Option<ChapterPosition<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChapterPosition<'a> {
+    pub slug: &'a str,
+    /// zero-based index among navigable pages of the chapter
+    pub index: usize,
+    pub total: usize,
+}
+
+/// Navigation for every page of a volume, computed once from the chapter tree
+#[derive(Debug)]
+pub struct Navigation<'a> {
+    pages: HashMap<&'a str, PageNavigation<'a>>,
+}
+
+impl<'a> Navigation<'a> {
+    /// Skipped pages still get prev/next links to the closest navigable pages, but no position
+    pub fn build(tree: &VolumeTree<'a>, rule: NavigationRule) -> Self {
+        let sequence: Vec<&'a str> = tree
+            .pages()
+            .filter(|node| rule.includes(node.page))
+            .map(|node| node.page.slug.as_str())
+            .collect();
+
+        let mut chapter_positions = HashMap::<&str, ChapterPosition>::new();
+        for chapter in tree.chapters() {
+            let included: Vec<&str> = chapter
+                .pages
+                .iter()
+                .filter(|node| rule.includes(node.page))
+                .map(|node| node.page.slug.as_str())
+                .collect();
+            for (index, slug) in included.iter().enumerate() {
+                chapter_positions.insert(
+                    slug,
+                    ChapterPosition {
+                        slug: chapter.slug,
+                        index,
+                        total: included.len(),
+                    },
+                );
+            }
+        }
+
+        let mut pages = HashMap::new();
+        // number of navigable pages before the current one
+        let mut before: usize = 0;
+        for node in tree.pages() {
+            let slug = node.page.slug.as_str();
+            let navigation = if rule.includes(node.page) {
+                let navigation = PageNavigation {
+                    prev_slug: before.checked_sub(1).map(|i| sequence[i]),
+                    next_slug: sequence.get(before + 1).copied(),
+                    position: Some(PagePosition {
+                        index: before,
+                        total: sequence.len(),
+                        chapter: chapter_positions.remove(slug),
+                    }),
+                };
+                before += 1;
+                navigation
+            } else {
+                PageNavigation {
+                    prev_slug: before.checked_sub(1).map(|i| sequence[i]),
+                    next_slug: sequence.get(before).copied(),
+                    position: None,
+                }
+            };
+            pages.insert(slug, navigation);
+        }
+
+        Self { pages }
+    }
+
+    pub fn get(&self, page_slug: &str) -> Option<&PageNavigation<'a>> {
+        self.pages.get(page_slug)
+    }
+}
@@ -1,11 +1,16 @@
+use std::str::FromStr;
+
 use serde::Serialize;
 
-use super::ChunkData;
+use super::{
+    assignment::{Assignment, AssignmentSettings},
+    ChunkData,
+};
 
 #[derive(Debug, Serialize)]
 pub struct PageParent {
-    title: String,
-    slug: String,
+    pub title: String,
+    pub slug: String,
 }
 
 impl PageParent {
@@ -25,11 +30,26 @@ pub struct PageData {
     /// parent page, if any
     pub parent: Option<PageParent>,
 
+    /// short preface for accessibility and SEO, extracted from the first chunk when unset
+    pub description: Option<String>,
+
+    /// SEO keywords
+    pub keywords: Vec<String>,
+
+    /// URL of the cover image
+    pub cover_image: Option<String>,
+
     /// relative order in the volume
     pub order: usize,
 
-    /// evaluation assignments, ["summary", "quiz", ...]
-    pub assignments: Vec<String>,
+    /// readable without signing in, resolved from the volume's free pages
+    pub free: bool,
+
+    /// evaluation assignments, serialized as ["summary", "quiz", ...]
+    pub assignments: Vec<Assignment>,
+
+    /// volume assignment settings overridden by the page's, for assignments added later
+    pub assignment_settings: AssignmentSettings,
 
     // quiz
     pub quiz: Option<Vec<QuizItem>>,
@@ -41,16 +61,91 @@ pub struct PageData {
     pub chunks: Vec<ChunkData>,
 }
 
-#[derive(Debug, Serialize, serde::Deserialize)]
+#[derive(Debug, Serialize)]
 pub struct QuizItem {
+    /// Strapi component id prefixed by its component, e.g. `mc-12`, or the id given in
+    /// generated questions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub question_type: QuestionType,
     pub question: String,
+    /// choices, or the accepted responses of a short answer question
     pub answers: Vec<QuizAnswerItem>,
+    /// shown after the question is answered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<Difficulty>,
+    /// learning objectives the question assesses
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub objectives: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionType {
+    SingleChoice,
+    MultipleSelect,
+    TrueFalse,
+    ShortAnswer,
+}
+
+impl QuestionType {
+    /// Type of a question that doesn't declare one, multiple select when several answers are correct
+    pub fn infer(answers: &[QuizAnswerItem]) -> Self {
+        if answers.iter().filter(|a| a.correct).count() > 1 {
+            QuestionType::MultipleSelect
+        } else {
+            QuestionType::SingleChoice
+        }
+    }
+}
+
+impl FromStr for QuestionType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace(['-', ' '], "_").as_str() {
+            "single_choice" | "multiple_choice" => Ok(QuestionType::SingleChoice),
+            "multiple_select" | "multiple_response" => Ok(QuestionType::MultipleSelect),
+            "true_false" => Ok(QuestionType::TrueFalse),
+            "short_answer" => Ok(QuestionType::ShortAnswer),
+            other => anyhow::bail!("unknown question type '{}'", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl FromStr for Difficulty {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            other => anyhow::bail!("unknown difficulty '{}'", other),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, serde::Deserialize)]
 pub struct QuizAnswerItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub answer: String,
     pub correct: bool,
+    /// shown when this answer is chosen
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback: Option<String>,
 }
 
 #[derive(Debug, Serialize, serde::Deserialize)]
@@ -0,0 +1,518 @@
+use std::io::{Seek, Write};
+
+use anyhow::{bail, Context, Result};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use super::{
+    fetch::VolumeData,
+    html::escape,
+    page::{PageData, QuestionType, QuizItem},
+    tree::{PageNode, TreeNode, VolumeTree},
+};
+
+const QTI_NAMESPACE: &str = "http://www.imsglobal.org/xsd/imsqti_v2p1";
+
+/// Builds an XML identifier from a slug. Identifiers are NCNames, which can't start with a
+/// digit or contain characters such as `~`, so `prefix` comes first and others become `_`.
+pub(crate) fn identifier(prefix: &str, slug: &str) -> String {
+    let slug: String = slug
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect();
+    format!("{}-{}", prefix, slug)
+}
+
+/// Writes every quiz of the volume as a QTI 2.1 content package.
+///
+/// The package holds one assessment test with a section per chapter and a nested section
+/// per page, each question is a separate item.
+pub fn export_qti<W: Write + Seek>(
+    volume: &VolumeData,
+    pages: &[PageData],
+    writer: W,
+) -> Result<()> {
+    let tree = VolumeTree::build(pages);
+    let mut items = Vec::<(String, String)>::new();
+    let mut sections = String::new();
+
+    let mut page_section = |node: &PageNode, indent: &str| -> Result<String> {
+        let page = node.page;
+        let Some(quiz) = page.quiz.as_ref().filter(|quiz| !quiz.is_empty()) else {
+            return Ok(String::new());
+        };
+
+        let mut refs = String::new();
+        for (index, item) in quiz.iter().enumerate() {
+            let ident = identifier("item", &format!("{}-q{}", page.slug, index + 1));
+            let xml = assessment_item(&ident, item)
+                .context(format!("failed to convert quiz of page '{}'", page.slug))?;
+            refs.push_str(&format!(
+                "{indent}  <assessmentItemRef identifier=\"{ident}\" href=\"items/{ident}.xml\"/>\n"
+            ));
+            items.push((ident, xml));
+        }
+
+        Ok(format!(
+            "{indent}<assessmentSection identifier=\"{}\" title=\"{}\" visible=\"true\">\n{refs}{indent}</assessmentSection>\n",
+            identifier("page", &page.slug),
+            escape(&page.title)
+        ))
+    };
+
+    for node in &tree.nodes {
+        match node {
+            TreeNode::Chapter(chapter) => {
+                let mut nested = String::new();
+                for page in &chapter.pages {
+                    nested.push_str(&page_section(page, "      ")?);
+                }
+                if !nested.is_empty() {
+                    sections.push_str(&format!(
+                        "    <assessmentSection identifier=\"{}\" title=\"{}\" visible=\"true\">\n{nested}    </assessmentSection>\n",
+                        identifier("chapter", chapter.slug),
+                        escape(chapter.title)
+                    ));
+                }
+            }
+            TreeNode::Page(page) => sections.push_str(&page_section(page, "    ")?),
+        }
+    }
+
+    if items.is_empty() {
+        bail!("volume '{}' has no quizzes to export", volume.slug);
+    }
+
+    let mut zip = ZipWriter::new(writer);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("imsmanifest.xml", options)?;
+    zip.write_all(qti_manifest(volume, &items).as_bytes())?;
+
+    zip.start_file("assessment.xml", options)?;
+    zip.write_all(
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<assessmentTest xmlns="{QTI_NAMESPACE}" identifier="{ident}" title="{title}">
+  <testPart identifier="quizzes" navigationMode="nonlinear" submissionMode="individual">
+{sections}  </testPart>
+</assessmentTest>
+"#,
+            ident = identifier("itell", &volume.slug),
+            title = escape(&volume.title),
+        )
+        .as_bytes(),
+    )?;
+
+    for (ident, xml) in &items {
+        zip.start_file(format!("items/{}.xml", ident), options)?;
+        zip.write_all(xml.as_bytes())?;
+    }
+
+    zip.finish().context("failed to finish QTI package")?;
+    Ok(())
+}
+
+/// Converts a page quiz to a QTI 1.2 assessment following the Common Cartridge profile.
+///
+/// Multiple select questions only score when exactly the correct answers are selected, short
+/// answers are fill-in-the-blank items that accept any correct answer regardless of case.
+pub fn quiz_to_qti(ident: &str, title: &str, quiz: &[QuizItem]) -> Result<String> {
+    let mut items = String::new();
+    for (index, item) in quiz.iter().enumerate() {
+        items.push_str(&qti_item(&format!("{}-q{}", ident, index + 1), item)?);
+    }
+
+    Ok(format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<questestinterop xmlns="http://www.imsglobal.org/xsd/ims_qtiasiv1p2" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.imsglobal.org/xsd/ims_qtiasiv1p2 http://www.imsglobal.org/profile/cc/ccv1p1/ccv1p1_qtiasiv1p2p1_v1p0.xsd">
+  <assessment ident="{ident}" title="{title}">
+    <qtimetadata>
+      <qtimetadatafield>
+        <fieldlabel>cc_profile</fieldlabel>
+        <fieldentry>cc.exam.v0p1</fieldentry>
+      </qtimetadatafield>
+      <qtimetadatafield>
+        <fieldlabel>qmd_assessmenttype</fieldlabel>
+        <fieldentry>Examination</fieldentry>
+      </qtimetadatafield>
+    </qtimetadata>
+    <section ident="{ident}-section">
+{items}    </section>
+  </assessment>
+</questestinterop>
+"#,
+        ident = escape(ident),
+        title = escape(title),
+    ))
+}
+
+/// Indices of the correct answers, a question without one can't be graded
+fn correct_answers(item: &QuizItem) -> Result<Vec<usize>> {
+    let correct: Vec<usize> = item
+        .answers
+        .iter()
+        .enumerate()
+        .filter(|(_, answer)| answer.correct)
+        .map(|(index, _)| index)
+        .collect();
+    if correct.is_empty() {
+        bail!("quiz question '{}' has no correct answer", item.question);
+    }
+    Ok(correct)
+}
+
+fn qti_item(ident: &str, item: &QuizItem) -> Result<String> {
+    let correct = correct_answers(item)?;
+    let varequal = |index: usize| {
+        format!(
+            r#"<varequal respident="{ident}-response">{ident}-a{}</varequal>"#,
+            index + 1
+        )
+    };
+
+    let (profile, response, condition) = match item.question_type {
+        QuestionType::ShortAnswer => {
+            let accepted: String = correct
+                .iter()
+                .map(|&index| {
+                    format!(
+                        r#"<varequal respident="{ident}-response" case="No">{}</varequal>"#,
+                        escape(&item.answers[index].answer)
+                    )
+                })
+                .collect();
+            (
+                "cc.fib.v0p1",
+                format!(
+                    r#"          <response_str ident="{ident}-response" rcardinality="Single">
+            <render_fib/>
+          </response_str>
+"#
+                ),
+                format!("<or>{}</or>", accepted),
+            )
+        }
+        question_type => {
+            let labels: String = item
+                .answers
+                .iter()
+                .enumerate()
+                .map(|(index, answer)| {
+                    format!(
+                        r#"              <response_label ident="{ident}-a{}">
+                <material><mattext texttype="text/plain">{}</mattext></material>
+              </response_label>
+"#,
+                        index + 1,
+                        escape(&answer.answer)
+                    )
+                })
+                .collect();
+
+            // multiple select only scores when every correct answer and no wrong one is selected
+            let (profile, cardinality, condition) = match question_type {
+                QuestionType::MultipleSelect => {
+                    let conditions: String = (0..item.answers.len())
+                        .map(|index| match item.answers[index].correct {
+                            true => varequal(index),
+                            false => format!("<not>{}</not>", varequal(index)),
+                        })
+                        .collect();
+                    (
+                        "cc.multiple_response.v0p1",
+                        "Multiple",
+                        format!("<and>{}</and>", conditions),
+                    )
+                }
+                QuestionType::TrueFalse => ("cc.true_false.v0p1", "Single", varequal(correct[0])),
+                _ => {
+                    let conditions: String = correct.iter().map(|&index| varequal(index)).collect();
+                    (
+                        "cc.multiple_choice.v0p1",
+                        "Single",
+                        format!("<or>{}</or>", conditions),
+                    )
+                }
+            };
+
+            (
+                profile,
+                format!(
+                    r#"          <response_lid ident="{ident}-response" rcardinality="{cardinality}">
+            <render_choice>
+{labels}            </render_choice>
+          </response_lid>
+"#
+                ),
+                condition,
+            )
+        }
+    };
+
+    // feedback conditions come first, scoring stops processing
+    let mut feedback_conditions = String::new();
+    let mut feedback = String::new();
+    if item.question_type != QuestionType::ShortAnswer {
+        for (index, answer) in item.answers.iter().enumerate() {
+            let Some(text) = &answer.feedback else {
+                continue;
+            };
+            let feedback_ident = format!("{ident}-a{}-feedback", index + 1);
+            feedback_conditions.push_str(&format!(
+                r#"          <respcondition continue="Yes">
+            <conditionvar>{}</conditionvar>
+            <displayfeedback feedbacktype="Response" linkrefid="{feedback_ident}"/>
+          </respcondition>
+"#,
+                varequal(index)
+            ));
+            feedback.push_str(&item_feedback(&feedback_ident, text));
+        }
+    }
+    if let Some(explanation) = &item.explanation {
+        feedback_conditions.push_str(&format!(
+            r#"          <respcondition continue="Yes">
+            <conditionvar><other/></conditionvar>
+            <displayfeedback feedbacktype="Response" linkrefid="{ident}-explanation"/>
+          </respcondition>
+"#
+        ));
+        feedback.push_str(&item_feedback(&format!("{ident}-explanation"), explanation));
+    }
+
+    Ok(format!(
+        r#"      <item ident="{ident}">
+        <itemmetadata>
+          <qtimetadata>
+            <qtimetadatafield>
+              <fieldlabel>cc_profile</fieldlabel>
+              <fieldentry>{profile}</fieldentry>
+            </qtimetadatafield>
+            <qtimetadatafield>
+              <fieldlabel>cc_weighting</fieldlabel>
+              <fieldentry>1</fieldentry>
+            </qtimetadatafield>
+          </qtimetadata>
+        </itemmetadata>
+        <presentation>
+          <material><mattext texttype="text/plain">{question}</mattext></material>
+{response}        </presentation>
+        <resprocessing>
+          <outcomes>
+            <decvar varname="SCORE" vartype="Decimal" minvalue="0" maxvalue="100"/>
+          </outcomes>
+{feedback_conditions}          <respcondition continue="No">
+            <conditionvar>{condition}</conditionvar>
+            <setvar varname="SCORE" action="Set">100</setvar>
+          </respcondition>
+        </resprocessing>
+{feedback}      </item>
+"#,
+        question = escape(&item.question),
+    ))
+}
+
+fn item_feedback(ident: &str, text: &str) -> String {
+    format!(
+        r#"        <itemfeedback ident="{ident}">
+          <flow_mat><material><mattext texttype="text/plain">{}</mattext></material></flow_mat>
+        </itemfeedback>
+"#,
+        escape(text)
+    )
+}
+
+/// A QTI 2.1 item, choices for choice questions and a text entry for short answers.
+/// Answer feedback is shown inline for the chosen answers, the explanation always.
+fn assessment_item(ident: &str, item: &QuizItem) -> Result<String> {
+    let correct = correct_answers(item)?;
+    let question = escape(&item.question);
+
+    let (declaration, body, scoring) = match item.question_type {
+        QuestionType::ShortAnswer => {
+            let entries: String = correct
+                .iter()
+                .map(|&index| {
+                    format!(
+                        r#"<mapEntry mapKey="{}" mappedValue="1" caseSensitive="false"/>"#,
+                        escape(&item.answers[index].answer)
+                    )
+                })
+                .collect();
+            (
+                format!(
+                    r#"  <responseDeclaration identifier="RESPONSE" cardinality="single" baseType="string">
+    <correctResponse><value>{}</value></correctResponse>
+    <mapping defaultValue="0" lowerBound="0" upperBound="1">{entries}</mapping>
+  </responseDeclaration>
+"#,
+                    escape(&item.answers[correct[0]].answer)
+                ),
+                format!(
+                    "    <p>{question}</p>\n    <p><textEntryInteraction responseIdentifier=\"RESPONSE\" expectedLength=\"30\"/></p>\n"
+                ),
+                r#"<setOutcomeValue identifier="SCORE"><mapResponse identifier="RESPONSE"/></setOutcomeValue>"#.to_string(),
+            )
+        }
+        question_type => {
+            let multiple = question_type == QuestionType::MultipleSelect;
+            let choices: String = item
+                .answers
+                .iter()
+                .enumerate()
+                .map(|(index, answer)| {
+                    let feedback = answer
+                        .feedback
+                        .as_deref()
+                        .map(|f| {
+                            format!(
+                                r#"<feedbackInline outcomeIdentifier="FEEDBACK" identifier="A{}" showHide="show">{}</feedbackInline>"#,
+                                index + 1,
+                                escape(f)
+                            )
+                        })
+                        .unwrap_or_default();
+                    format!(
+                        "      <simpleChoice identifier=\"A{}\">{}{}</simpleChoice>\n",
+                        index + 1,
+                        escape(&answer.answer),
+                        feedback
+                    )
+                })
+                .collect();
+            let body = format!(
+                "    <choiceInteraction responseIdentifier=\"RESPONSE\" shuffle=\"false\" maxChoices=\"{}\">\n      <prompt>{question}</prompt>\n{choices}    </choiceInteraction>\n",
+                if multiple { 0 } else { 1 }
+            );
+
+            // multiple select needs the exact set, single choice accepts any correct answer
+            if multiple {
+                let values: String = correct
+                    .iter()
+                    .map(|index| format!("<value>A{}</value>", index + 1))
+                    .collect();
+                (
+                    format!(
+                        r#"  <responseDeclaration identifier="RESPONSE" cardinality="multiple" baseType="identifier">
+    <correctResponse>{values}</correctResponse>
+  </responseDeclaration>
+"#
+                    ),
+                    body,
+                    r#"<responseCondition><responseIf><match><variable identifier="RESPONSE"/><correct identifier="RESPONSE"/></match><setOutcomeValue identifier="SCORE"><baseValue baseType="float">1</baseValue></setOutcomeValue></responseIf></responseCondition>"#.to_string(),
+                )
+            } else {
+                let entries: String = correct
+                    .iter()
+                    .map(|index| format!(r#"<mapEntry mapKey="A{}" mappedValue="1"/>"#, index + 1))
+                    .collect();
+                (
+                    format!(
+                        r#"  <responseDeclaration identifier="RESPONSE" cardinality="single" baseType="identifier">
+    <correctResponse><value>A{}</value></correctResponse>
+    <mapping defaultValue="0" lowerBound="0" upperBound="1">{entries}</mapping>
+  </responseDeclaration>
+"#,
+                        correct[0] + 1
+                    ),
+                    body,
+                    r#"<setOutcomeValue identifier="SCORE"><mapResponse identifier="RESPONSE"/></setOutcomeValue>"#.to_string(),
+                )
+            }
+        }
+    };
+
+    let mut outcomes = String::new();
+    let mut processing = scoring;
+    if item.question_type != QuestionType::ShortAnswer {
+        let cardinality = match item.question_type {
+            QuestionType::MultipleSelect => "multiple",
+            _ => "single",
+        };
+        outcomes.push_str(&format!(
+            "  <outcomeDeclaration identifier=\"FEEDBACK\" cardinality=\"{cardinality}\" baseType=\"identifier\"/>\n"
+        ));
+        processing.push_str(
+            r#"<setOutcomeValue identifier="FEEDBACK"><variable identifier="RESPONSE"/></setOutcomeValue>"#,
+        );
+    }
+    let mut modal = String::new();
+    if let Some(explanation) = &item.explanation {
+        outcomes.push_str(
+            "  <outcomeDeclaration identifier=\"EXPLANATION\" cardinality=\"single\" baseType=\"identifier\"/>\n",
+        );
+        processing.push_str(
+            r#"<setOutcomeValue identifier="EXPLANATION"><baseValue baseType="identifier">explanation</baseValue></setOutcomeValue>"#,
+        );
+        modal = format!(
+            "  <modalFeedback outcomeIdentifier=\"EXPLANATION\" identifier=\"explanation\" showHide=\"show\">{}</modalFeedback>\n",
+            escape(explanation)
+        );
+    }
+
+    Ok(format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<assessmentItem xmlns="{QTI_NAMESPACE}" identifier="{ident}" title="{question}" adaptive="false" timeDependent="false">
+{declaration}  <outcomeDeclaration identifier="SCORE" cardinality="single" baseType="float">
+    <defaultValue><value>0</value></defaultValue>
+  </outcomeDeclaration>
+{outcomes}  <itemBody>
+{body}  </itemBody>
+  <responseProcessing>{processing}</responseProcessing>
+{modal}</assessmentItem>
+"#,
+        ident = escape(ident),
+    ))
+}
+
+fn qti_manifest(volume: &VolumeData, items: &[(String, String)]) -> String {
+    let dependencies: String = items
+        .iter()
+        .map(|(ident, _)| format!("      <dependency identifierref=\"{}\"/>\n", escape(ident)))
+        .collect();
+    let resources: String = items
+        .iter()
+        .map(|(ident, _)| {
+            format!(
+                r#"    <resource identifier="{ident}" type="imsqti_item_xmlv2p1" href="items/{ident}.xml">
+      <file href="items/{ident}.xml"/>
+    </resource>
+"#,
+                ident = escape(ident)
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest identifier="{ident}" xmlns="http://www.imsglobal.org/xsd/imscp_v1p1">
+  <metadata>
+    <schema>QTIv2.1 Package</schema>
+    <schemaversion>1.0.0</schemaversion>
+  </metadata>
+  <organizations/>
+  <resources>
+    <resource identifier="assessment" type="imsqti_test_xmlv2p1" href="assessment.xml">
+      <file href="assessment.xml"/>
+{dependencies}    </resource>
+{resources}  </resources>
+</manifest>
+"#,
+        ident = identifier("itell", &format!("{}-qti", volume.slug)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identifiers_are_ncnames() {
+        assert_eq!(identifier("page", "1-intro"), "page-1-intro");
+        assert_eq!(identifier("item", "a~b/c d-q1"), "item-a_b_c_d-q1");
+        assert_eq!(identifier("chapter", "über.2"), "chapter-_ber.2");
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{
+    page::{PageData, QuestionType, QuizItem},
+    validate::{Diagnostic, Severity},
+};
+
+/// Checks that every quiz question can be answered and graded.
+///
+/// Errors cover missing text, too few answers, duplicate answers, correct answer counts that
+/// don't fit the question type and question ids reused within the volume. Duplicate questions on
+/// a page and answers that have no effect are warnings.
+pub fn validate_quizzes(pages: &[PageData]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut ids = BTreeMap::<&str, Vec<&str>>::new();
+
+    for page in pages {
+        let Some(quiz) = &page.quiz else {
+            continue;
+        };
+
+        let mut questions = HashMap::<String, usize>::new();
+        for (index, item) in quiz.iter().enumerate() {
+            let label = question_label(index, item);
+            for (severity, message) in check_question(item) {
+                let message = format!("{} {}", label, message);
+                diagnostics.push(match severity {
+                    Severity::Error => Diagnostic::error(Some(&page.slug), None, message),
+                    Severity::Warning => Diagnostic::warning(Some(&page.slug), None, message),
+                });
+            }
+
+            let question = normalize(&item.question);
+            if let Some(first) = questions.get(&question).filter(|_| !question.is_empty()) {
+                diagnostics.push(Diagnostic::warning(
+                    Some(&page.slug),
+                    None,
+                    format!("{} repeats quiz question {}", label, first + 1),
+                ));
+            } else {
+                questions.insert(question, index);
+            }
+
+            if let Some(id) = &item.id {
+                ids.entry(id.as_str()).or_default().push(page.slug.as_str());
+            }
+        }
+    }
+
+    for (id, slugs) in ids.iter_mut().filter(|(_, slugs)| slugs.len() > 1) {
+        let count = slugs.len();
+        slugs.dedup();
+        diagnostics.push(Diagnostic::error(
+            None,
+            None,
+            format!(
+                "Quiz question id '{}' is used {} times, on pages '{}'",
+                id,
+                count,
+                slugs.join("', '")
+            ),
+        ));
+    }
+
+    diagnostics
+}
+
+fn question_label(index: usize, item: &QuizItem) -> String {
+    match &item.id {
+        Some(id) => format!("Quiz question {} ('{}')", index + 1, id),
+        None => format!("Quiz question {}", index + 1),
+    }
+}
+
+/// Problems of a single question
+fn check_question(item: &QuizItem) -> Vec<(Severity, String)> {
+    let mut problems = Vec::new();
+
+    if item.question.trim().is_empty() {
+        problems.push((Severity::Error, "has no question text".to_string()));
+    }
+
+    let minimum = match item.question_type {
+        QuestionType::ShortAnswer => 1,
+        _ => 2,
+    };
+    // true/false is checked for exactly two answers below
+    if item.answers.len() < minimum && item.question_type != QuestionType::TrueFalse {
+        problems.push((
+            Severity::Error,
+            format!(
+                "has {} answers, {} questions need at least {}",
+                item.answers.len(),
+                type_name(item.question_type),
+                minimum
+            ),
+        ));
+    }
+
+    let mut texts = HashMap::<String, usize>::new();
+    for (index, answer) in item.answers.iter().enumerate() {
+        let text = normalize(&answer.answer);
+        if text.is_empty() {
+            problems.push((Severity::Error, format!("answer {} has no text", index + 1)));
+        } else if let Some(first) = texts.get(&text) {
+            problems.push((
+                Severity::Error,
+                format!(
+                    "answer {} duplicates answer {} ('{}')",
+                    index + 1,
+                    first + 1,
+                    answer.answer.trim()
+                ),
+            ));
+        } else {
+            texts.insert(text, index);
+        }
+    }
+
+    let correct = item.answers.iter().filter(|a| a.correct).count();
+    match item.question_type {
+        QuestionType::SingleChoice | QuestionType::TrueFalse if correct != 1 => {
+            problems.push((
+                Severity::Error,
+                format!(
+                    "has {} correct answers, {} questions need exactly one",
+                    correct,
+                    type_name(item.question_type)
+                ),
+            ));
+        }
+        QuestionType::MultipleSelect | QuestionType::ShortAnswer if correct == 0 => {
+            problems.push((Severity::Error, "has no correct answer".to_string()));
+        }
+        QuestionType::MultipleSelect if correct == 1 => {
+            problems.push((
+                Severity::Warning,
+                "has a single correct answer, it could be a single choice question".to_string(),
+            ));
+        }
+        QuestionType::MultipleSelect if correct == item.answers.len() => {
+            problems.push((
+                Severity::Warning,
+                "has no wrong answer to tell apart".to_string(),
+            ));
+        }
+        QuestionType::ShortAnswer if correct < item.answers.len() => {
+            problems.push((
+                Severity::Warning,
+                "lists wrong answers, short answer questions only accept the correct ones"
+                    .to_string(),
+            ));
+        }
+        _ => {}
+    }
+
+    if item.question_type == QuestionType::TrueFalse && item.answers.len() != 2 {
+        problems.push((
+            Severity::Error,
+            format!(
+                "has {} answers, true/false questions need exactly two",
+                item.answers.len()
+            ),
+        ));
+    }
+
+    problems
+}
+
+fn type_name(question_type: QuestionType) -> &'static str {
+    match question_type {
+        QuestionType::SingleChoice => "single choice",
+        QuestionType::MultipleSelect => "multiple select",
+        QuestionType::TrueFalse => "true/false",
+        QuestionType::ShortAnswer => "short answer",
+    }
+}
+
+/// Case- and whitespace-insensitive form used to compare texts
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::{page::QuizAnswerItem, validate::has_errors};
+
+    fn question(id: &str) -> QuizItem {
+        QuizItem {
+            id: Some(id.to_string()),
+            question_type: QuestionType::SingleChoice,
+            question: format!("Question {}?", id),
+            answers: [("Yes", true), ("No", false)]
+                .into_iter()
+                .map(|(answer, correct)| QuizAnswerItem {
+                    id: None,
+                    answer: answer.to_string(),
+                    correct,
+                    feedback: None,
+                })
+                .collect(),
+            explanation: None,
+            difficulty: None,
+            objectives: vec![],
+        }
+    }
+
+    fn page(slug: &str, quiz: Vec<QuizItem>) -> PageData {
+        PageData {
+            title: slug.to_string(),
+            slug: slug.to_string(),
+            parent: None,
+            description: None,
+            keywords: vec![],
+            cover_image: None,
+            order: 0,
+            free: false,
+            assignments: vec![],
+            assignment_settings: Default::default(),
+            quiz: Some(quiz),
+            cloze_test: None,
+            chunks: vec![],
+        }
+    }
+
+    #[test]
+    fn ids_of_different_components_are_distinct() {
+        let pages = [
+            page("a", vec![question("mc-12")]),
+            page("b", vec![question("gen-12")]),
+        ];
+        assert!(validate_quizzes(&pages).is_empty());
+    }
+
+    #[test]
+    fn ids_must_be_unique_in_the_volume() {
+        let pages = [
+            page("a", vec![question("mc-12")]),
+            page("b", vec![question("mc-12")]),
+        ];
+        let diagnostics = validate_quizzes(&pages);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(has_errors(&diagnostics));
+    }
+}
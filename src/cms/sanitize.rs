@@ -0,0 +1,506 @@
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+use serde::Serialize;
+
+use super::{
+    markdown::{split_fences, Segment},
+    page::PageData,
+};
+
+/// Raw HTML tags that may appear in chunk markdown, `i-*` components are always allowed
+const ALLOWED_TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "cite",
+    "code",
+    "dd",
+    "del",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "s",
+    "section",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "u",
+    "ul",
+];
+
+/// Elements that are removed together with their content
+const DANGEROUS_ELEMENTS: &[&str] = &["script", "style", "iframe", "object", "embed"];
+
+static ELEMENT_REGEXES: LazyLock<Vec<(&str, Regex)>> = LazyLock::new(|| {
+    DANGEROUS_ELEMENTS
+        .iter()
+        .map(|tag| {
+            let element = format!(r"(?is)^<{tag}\b[^>]*>.*?</\s*{tag}\s*>");
+            (*tag, Regex::new(&element).unwrap())
+        })
+        .collect()
+});
+static COMMENT_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)^<!--.*?-->").unwrap());
+static TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^<(/?)([A-Za-z][A-Za-z0-9-]*)([\s/](?:[^>"']|"[^"]*"|'[^']*')*)?>"#).unwrap()
+});
+static AUTOLINK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^<\s*([A-Za-z][A-Za-z0-9+.-]*:[^<>]*|[^\s<>@]+@[^\s<>@]+)>").unwrap()
+});
+static LINK_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\]\(\s*<?\s*)((?:[^()<>\s]|\([^()]*\))+)").unwrap());
+static DEFINITION_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\s{0,3}\[[^\]]+\]:\s*<?)([^<>\s]+)").unwrap());
+static ATTRIBUTE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"([^\s=/]+)(?:\s*=\s*("[^"]*"|'[^']*'|\{[^}]*\}|[^\s"'>]+))?"#).unwrap()
+});
+static ESCAPE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\(\p{Punct})").unwrap());
+static REFERENCE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"&(?:#[xX]([0-9A-Fa-f]+);?|#([0-9]+);?|([A-Za-z]+);)").unwrap());
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Removal {
+    /// a dangerous element removed together with its content, e.g. `<script>`
+    Element { tag: String },
+    /// an inline event handler attribute such as `onclick`
+    EventHandler { tag: String, attribute: String },
+    /// a `style` attribute, which can load URLs and overlay the page
+    Style { tag: String },
+    /// a `javascript:` URL in an attribute or a markdown link
+    JavascriptUrl { url: String },
+    /// a tag that is not allowlisted, its content is kept
+    Tag { tag: String },
+}
+
+#[derive(Debug, Serialize)]
+pub struct SanitizeReport {
+    pub page_slug: String,
+    pub chunk_slug: String,
+    pub removed: Vec<Removal>,
+}
+
+/// Sanitizes the markdown of every chunk in place, returns a report for each chunk that was changed
+pub fn sanitize_pages(pages: &mut [PageData]) -> Vec<SanitizeReport> {
+    let mut reports = Vec::new();
+
+    for page in pages.iter_mut() {
+        for chunk in page.chunks.iter_mut() {
+            let (content, removed) = sanitize_markdown(&chunk.content);
+            // the prompt is also at the start of the content, so its removals are reported once
+            if let Some(exercise) = &mut chunk.exercise {
+                exercise.prompt = sanitize_markdown(&exercise.prompt).0;
+            }
+            if removed.is_empty() {
+                continue;
+            }
+
+            chunk.content = content;
+            reports.push(SanitizeReport {
+                page_slug: page.slug.clone(),
+                chunk_slug: chunk.slug.clone(),
+                removed,
+            });
+        }
+    }
+
+    reports
+}
+
+/// Strips scripts, event handlers, `javascript:` URLs and non-allowlisted HTML from markdown.
+/// Code blocks and inline code are left untouched since they are rendered as text.
+pub fn sanitize_markdown(content: &str) -> (String, Vec<Removal>) {
+    let mut removed = Vec::new();
+    let mut output = String::with_capacity(content.len());
+
+    for segment in split_fences(content) {
+        match segment {
+            Segment::Code(code) => output.push_str(code),
+            Segment::Text(text) => output.push_str(&sanitize_text(text, &mut removed)),
+        }
+    }
+
+    (output, removed)
+}
+
+/// Scans prose from left to right like a markdown parser, whatever starts first wins: a backtick
+/// inside a tag doesn't hide the tag, and a tag inside a code span stays code. A `<` that doesn't
+/// start a tag is escaped, so removing a tag can't splice a new one together.
+fn sanitize_text(text: &str, removed: &mut Vec<Removal>) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut index = 0;
+
+    while index < text.len() {
+        let rest = &text[index..];
+
+        // reference definitions start a line
+        if index == 0 || text[..index].ends_with('\n') {
+            if let Some(caps) = DEFINITION_REGEX.captures(rest) {
+                output.push_str(&strip_markdown_url(&caps, removed));
+                index += caps[0].len();
+                continue;
+            }
+        }
+
+        let c = rest.chars().next().unwrap();
+        let consumed = match c {
+            '`' => {
+                let span = code_span(rest);
+                output.push_str(&rest[..span]);
+                span
+            }
+            ']' => match LINK_REGEX.captures(rest) {
+                Some(caps) => {
+                    output.push_str(&strip_markdown_url(&caps, removed));
+                    caps[0].len()
+                }
+                None => 0,
+            },
+            '<' => {
+                if let Some((tag, m)) = ELEMENT_REGEXES
+                    .iter()
+                    .find_map(|(tag, regex)| Some((tag, regex.find(rest)?)))
+                {
+                    removed.push(Removal::Element {
+                        tag: tag.to_string(),
+                    });
+                    m.len()
+                } else if let Some(m) = COMMENT_REGEX.find(rest) {
+                    m.len()
+                } else if let Some(caps) = TAG_REGEX.captures(rest) {
+                    output.push_str(&sanitize_tag(&caps, removed));
+                    caps[0].len()
+                } else if let Some(caps) = AUTOLINK_REGEX.captures(rest) {
+                    if is_javascript_url(&caps[1]) {
+                        removed.push(Removal::JavascriptUrl {
+                            url: caps[1].to_string(),
+                        });
+                    } else {
+                        output.push_str(&caps[0]);
+                    }
+                    caps[0].len()
+                } else {
+                    output.push_str("&lt;");
+                    1
+                }
+            }
+            _ => 0,
+        };
+
+        if consumed == 0 {
+            output.push(c);
+            index += c.len_utf8();
+        } else {
+            index += consumed;
+        }
+    }
+
+    output
+}
+
+/// Length of the code span at the start of `text`, or of its backtick run when it isn't closed
+/// within the paragraph
+fn code_span(text: &str) -> usize {
+    let run = text.len() - text.trim_start_matches('`').len();
+    let mut index = run;
+
+    while let Some(found) = text[index..].find('`') {
+        let start = index + found;
+        if text[run..start].contains("\n\n") {
+            break;
+        }
+        let len = text[start..].len() - text[start..].trim_start_matches('`').len();
+        if len == run {
+            return start + len;
+        }
+        index = start + len;
+    }
+
+    run
+}
+
+/// Replaces a `javascript:` destination of a markdown link or reference definition with `#`
+fn strip_markdown_url(caps: &Captures, removed: &mut Vec<Removal>) -> String {
+    // the markdown parser resolves backslash escapes before the browser sees the URL
+    let url = ESCAPE_REGEX.replace_all(&caps[2], "$1");
+    if !is_javascript_url(&url) {
+        return caps[0].to_string();
+    }
+
+    removed.push(Removal::JavascriptUrl {
+        url: caps[2].to_string(),
+    });
+    format!("{}#", &caps[1])
+}
+
+/// Drops non-allowlisted tags, and unsafe attributes from allowlisted ones
+fn sanitize_tag(caps: &Captures, removed: &mut Vec<Removal>) -> String {
+    let closing = &caps[1];
+    let name = caps[2].to_lowercase();
+
+    if !name.starts_with("i-") && !ALLOWED_TAGS.contains(&name.as_str()) {
+        if closing.is_empty() {
+            removed.push(Removal::Tag { tag: name });
+        }
+        return String::new();
+    }
+
+    let Some(attributes) = caps.get(3).map(|m| m.as_str()) else {
+        return caps[0].to_string();
+    };
+    let (attributes, self_closing) = match attributes.trim_end().strip_suffix('/') {
+        Some(rest) => (rest, true),
+        None => (attributes, false),
+    };
+
+    let mut kept = Vec::new();
+    let mut changed = false;
+
+    for attr in ATTRIBUTE_REGEX.captures_iter(attributes) {
+        let attr_name = attr[1].to_lowercase();
+        let value = attr.get(2).map_or("", |v| {
+            v.as_str()
+                .trim_matches(|c| c == '"' || c == '\'' || c == '{' || c == '}')
+        });
+
+        if attr_name.starts_with("on") {
+            removed.push(Removal::EventHandler {
+                tag: name.clone(),
+                attribute: attr_name,
+            });
+            changed = true;
+        } else if attr_name == "style" {
+            removed.push(Removal::Style { tag: name.clone() });
+            changed = true;
+        } else if is_javascript_url(value) {
+            removed.push(Removal::JavascriptUrl {
+                url: value.to_string(),
+            });
+            changed = true;
+        } else {
+            kept.push(attr[0].to_string());
+        }
+    }
+
+    if !changed {
+        return caps[0].to_string();
+    }
+
+    let mut tag = format!("<{}{}", closing, &caps[2]);
+    for attr in kept {
+        tag.push(' ');
+        tag.push_str(&attr);
+    }
+    if self_closing {
+        tag.push_str(" /");
+    }
+    tag.push('>');
+    tag
+}
+
+/// Browsers decode character references and ignore whitespace and control characters inside
+/// the scheme, so `java&#115;cript:` and `java\tscript:` are both `javascript:`
+fn is_javascript_url(value: &str) -> bool {
+    decode_references(value)
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_lowercase()
+        .starts_with("javascript:")
+}
+
+/// Decodes numeric character references, with or without the closing `;`, and the named
+/// references that can spell a URL scheme
+fn decode_references(value: &str) -> String {
+    REFERENCE_REGEX
+        .replace_all(value, |caps: &Captures| {
+            let code = match (caps.get(1), caps.get(2)) {
+                (Some(hex), _) => u32::from_str_radix(hex.as_str(), 16).ok(),
+                (_, Some(decimal)) => decimal.as_str().parse().ok(),
+                _ => {
+                    let decoded = match &caps[3] {
+                        "colon" => ":",
+                        "Tab" => "\t",
+                        "NewLine" => "\n",
+                        "nbsp" => "\u{a0}",
+                        _ => &caps[0],
+                    };
+                    return decoded.to_string();
+                }
+            };
+            code.and_then(char::from_u32)
+                .unwrap_or(char::REPLACEMENT_CHARACTER)
+                .to_string()
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cms::chunk::{ChunkData, ChunkType, Exercise, RevealMode};
+
+    fn sanitize(content: &str) -> String {
+        sanitize_markdown(content).0
+    }
+
+    #[test]
+    fn removes_script_elements() {
+        let (output, removed) = sanitize_markdown("Hi <script>alert(1)</script>there");
+        assert_eq!(output, "Hi there");
+        assert!(matches!(&removed[..], [Removal::Element { tag }] if tag == "script"));
+    }
+
+    #[test]
+    fn removes_event_handlers() {
+        let (output, removed) =
+            sanitize_markdown(r#"<img src="a.png" onerror="alert(1)" alt="A">"#);
+        assert_eq!(output, r#"<img src="a.png" alt="A">"#);
+        assert!(matches!(
+            &removed[..],
+            [Removal::EventHandler { attribute, .. }] if attribute == "onerror"
+        ));
+    }
+
+    #[test]
+    fn backtick_inside_a_tag_does_not_hide_it() {
+        let output = sanitize("<img src=x onerror=\"alert(1)//`x`\">");
+        assert_eq!(output, "<img src=x>");
+    }
+
+    #[test]
+    fn tags_inside_code_are_kept() {
+        let content =
+            "Use `<script>` or `` <img onerror=x> ``\n\n```html\n<script>x</script>\n```\n";
+        let (output, removed) = sanitize_markdown(content);
+        assert_eq!(output, content);
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn removes_encoded_javascript_urls() {
+        assert_eq!(
+            sanitize(r#"<a href="java&#115;cript:alert(1)">x</a>"#),
+            "<a>x</a>"
+        );
+        assert_eq!(
+            sanitize(r#"<a href="&#x6A;avascript&colon;alert(1)">x</a>"#),
+            "<a>x</a>"
+        );
+        assert_eq!(sanitize("[x](java&#115;cript:alert(1))"), "[x](#)");
+        assert_eq!(sanitize("[x](javascript\\:alert(1))"), "[x](#)");
+        assert_eq!(sanitize("[x]: java&#115;cript:alert(1)"), "[x]: #");
+        assert_eq!(sanitize("<javascript:alert(1)>"), "");
+    }
+
+    #[test]
+    fn sanitizes_exercise_prompts() {
+        let prompt = "Fix <b onclick=\"x()\">this</b><script>alert(1)</script>";
+        let chunk = ChunkData {
+            title: "Try it".to_string(),
+            slug: "try-it".to_string(),
+            depth: 2,
+            content: format!(
+                "{}\n\n<i-exercise chunk-slug=\"try-it\" language=\"js\">",
+                prompt
+            ),
+            cri: vec![],
+            show_header: true,
+            chunk_type: ChunkType::Exercise,
+            reveal: RevealMode::Standalone,
+            exercise: Some(Exercise {
+                slug: "try-it".to_string(),
+                language: "js".to_string(),
+                prompt: prompt.to_string(),
+                starter_code: None,
+                tests: None,
+                solution: None,
+            }),
+        };
+        let mut pages = [PageData {
+            title: "Page".to_string(),
+            slug: "page".to_string(),
+            parent: None,
+            description: None,
+            keywords: vec![],
+            cover_image: None,
+            order: 0,
+            free: false,
+            assignments: vec![],
+            assignment_settings: Default::default(),
+            quiz: None,
+            cloze_test: None,
+            chunks: vec![chunk],
+        }];
+
+        let reports = sanitize_pages(&mut pages);
+        assert_eq!(reports[0].removed.len(), 2);
+        let chunk = &pages[0].chunks[0];
+        assert_eq!(chunk.exercise.as_ref().unwrap().prompt, "Fix <b>this</b>");
+        assert!(chunk.content.starts_with("Fix <b>this</b>\n\n<i-exercise"));
+    }
+
+    #[test]
+    fn escapes_tags_spliced_together_by_a_removal() {
+        assert_eq!(
+            sanitize("<scr<x>ipt>alert(1)</scr<x>ipt>"),
+            "&lt;script>alert(1)&lt;/script>"
+        );
+        assert_eq!(sanitize("1 < 2"), "1 &lt; 2");
+    }
+
+    #[test]
+    fn removes_event_handlers_after_a_slash() {
+        assert_eq!(sanitize("<img/onerror=alert(1) src=x>"), "<img src=x>");
+    }
+
+    #[test]
+    fn removes_style_attributes() {
+        let (output, removed) =
+            sanitize_markdown(r#"<div style="background:url(javascript:alert(1))">x</div>"#);
+        assert_eq!(output, "<div>x</div>");
+        assert!(matches!(&removed[..], [Removal::Style { tag }] if tag == "div"));
+    }
+
+    #[test]
+    fn keeps_safe_urls() {
+        let content =
+            r#"[x](https://example.com/a_(b)) <a href="/java&amp;script">y</a> <a@b.org>"#;
+        assert_eq!(sanitize(content), content);
+    }
+}
@@ -0,0 +1,128 @@
+use serde::Serialize;
+
+use super::text::edit_distance;
+
+/// Minimum scores a learner response needs on each metric to pass
+#[derive(Debug, Clone, Copy)]
+pub struct CriThresholds {
+    pub rouge_l: f64,
+    /// only checked when the item has keywords
+    pub keyword_coverage: f64,
+    /// 0 by default, paraphrased answers rarely share much spelling with the reference
+    pub edit_similarity: f64,
+}
+
+impl Default for CriThresholds {
+    fn default() -> Self {
+        Self {
+            rouge_l: 0.3,
+            keyword_coverage: 0.5,
+            edit_similarity: 0.0,
+        }
+    }
+}
+
+/// Lexical overlap of a learner response with the reference answer, each metric in `0..=1`
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CriScore {
+    /// F1 of the longest common word subsequence
+    pub rouge_l: f64,
+    /// share of keywords found in the response, `None` without keywords
+    pub keyword_coverage: Option<f64>,
+    /// one minus the char edit distance over the longer text length
+    pub edit_similarity: f64,
+    pub passed: bool,
+}
+
+/// Scores a learner response against a constructed response item's reference answer and
+/// keywords. Texts are compared as lowercase words, ignoring punctuation.
+pub fn score_response(
+    reference: &str,
+    keywords: &[String],
+    response: &str,
+    thresholds: &CriThresholds,
+) -> CriScore {
+    let reference_words = words(reference);
+    let response_words = words(response);
+
+    let rouge_l = rouge_l(&reference_words, &response_words);
+    let keyword_coverage = keyword_coverage(keywords, &response_words);
+    let edit_similarity = edit_similarity(&reference_words.join(" "), &response_words.join(" "));
+
+    let passed = rouge_l >= thresholds.rouge_l
+        && keyword_coverage.is_none_or(|coverage| coverage >= thresholds.keyword_coverage)
+        && edit_similarity >= thresholds.edit_similarity;
+
+    CriScore {
+        rouge_l,
+        keyword_coverage,
+        edit_similarity,
+        passed,
+    }
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn rouge_l(reference: &[String], response: &[String]) -> f64 {
+    let lcs = longest_common_subsequence(reference, response);
+    if lcs == 0 {
+        return 0.0;
+    }
+
+    let precision = lcs as f64 / response.len() as f64;
+    let recall = lcs as f64 / reference.len() as f64;
+    2.0 * precision * recall / (precision + recall)
+}
+
+fn longest_common_subsequence(a: &[String], b: &[String]) -> usize {
+    let mut previous = vec![0; b.len() + 1];
+    let mut current = vec![0; b.len() + 1];
+
+    for word in a {
+        for (j, other) in b.iter().enumerate() {
+            current[j + 1] = if word == other {
+                previous[j] + 1
+            } else {
+                previous[j + 1].max(current[j])
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// A keyword of several words counts when they appear next to each other in the response
+fn keyword_coverage(keywords: &[String], response: &[String]) -> Option<f64> {
+    let keywords: Vec<Vec<String>> = keywords
+        .iter()
+        .map(|keyword| words(keyword))
+        .filter(|words| !words.is_empty())
+        .collect();
+    if keywords.is_empty() {
+        return None;
+    }
+
+    let found = keywords
+        .iter()
+        .filter(|keyword| {
+            response
+                .windows(keyword.len())
+                .any(|window| window == *keyword)
+        })
+        .count();
+    Some(found as f64 / keywords.len() as f64)
+}
+
+fn edit_similarity(reference: &str, response: &str) -> f64 {
+    let length = reference.chars().count().max(response.chars().count());
+    if length == 0 {
+        return 0.0;
+    }
+    1.0 - edit_distance(reference, response) as f64 / length as f64
+}
@@ -0,0 +1,165 @@
+use std::ops::AddAssign;
+
+use serde::Serialize;
+
+use super::{
+    chunk::{ChunkData, ChunkType},
+    markdown::plain_text,
+    tree::{TreeNode, VolumeTree},
+    PageData,
+};
+
+/// Average silent reading speed of adults reading non-fiction
+pub const WORDS_PER_MINUTE: usize = 238;
+
+/// Counts of a chunk, page, chapter or the whole volume
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct Stats {
+    /// words of prose, headings and code are not counted
+    pub words: usize,
+    /// estimated minutes to read the prose, rounded up
+    pub reading_time: usize,
+    pub chunks: usize,
+    pub cri: usize,
+    pub quiz: usize,
+    pub videos: usize,
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, other: Self) {
+        self.words += other.words;
+        self.chunks += other.chunks;
+        self.cri += other.cri;
+        self.quiz += other.quiz;
+        self.videos += other.videos;
+        // estimated from the total, rounding every part up would overcount
+        self.reading_time = reading_time(self.words);
+    }
+}
+
+/// Stats of the volume, grouped like the chapter tree
+#[derive(Debug, Serialize)]
+pub struct VolumeStats<'a> {
+    #[serde(flatten)]
+    pub stats: Stats,
+    pub items: Vec<StatsItem<'a>>,
+}
+
+/// Top-level entry, a chapter with its pages or a page without a chapter
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StatsItem<'a> {
+    Chapter {
+        title: &'a str,
+        slug: &'a str,
+        #[serde(flatten)]
+        stats: Stats,
+        pages: Vec<PageStats<'a>>,
+    },
+    Page(PageStats<'a>),
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageStats<'a> {
+    pub title: &'a str,
+    pub slug: &'a str,
+    #[serde(flatten)]
+    pub stats: Stats,
+    /// one entry per chunk
+    pub items: Vec<ChunkStats<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkStats<'a> {
+    pub title: &'a str,
+    pub slug: &'a str,
+    #[serde(flatten)]
+    pub stats: Stats,
+}
+
+/// Minutes to read `words`, at least one minute for any text
+pub fn reading_time(words: usize) -> usize {
+    words.div_ceil(WORDS_PER_MINUTE)
+}
+
+pub fn chunk_stats(chunk: &ChunkData) -> Stats {
+    let words = plain_text(&chunk.content).split_whitespace().count();
+    // video chunks embed their video, other chunks may embed more
+    let embedded = chunk.content.matches("<i-youtube").count();
+    let videos = match chunk.chunk_type {
+        ChunkType::Video => embedded.max(1),
+        _ => embedded,
+    };
+
+    Stats {
+        words,
+        reading_time: reading_time(words),
+        chunks: 1,
+        cri: chunk.cri.len(),
+        quiz: 0,
+        videos,
+    }
+}
+
+pub fn page_stats(page: &PageData) -> Stats {
+    let mut stats = Stats {
+        quiz: page.quiz.as_ref().map_or(0, Vec::len),
+        ..Stats::default()
+    };
+    for chunk in &page.chunks {
+        stats += chunk_stats(chunk);
+    }
+    stats
+}
+
+/// Builds the stats of every chunk, page and chapter of the volume
+pub fn build_stats(pages: &[PageData]) -> VolumeStats<'_> {
+    let mut stats = Stats::default();
+    let items = VolumeTree::build(pages)
+        .nodes
+        .iter()
+        .map(|node| match node {
+            TreeNode::Chapter(chapter) => {
+                let pages: Vec<PageStats> =
+                    chapter.pages.iter().map(|p| stats_page(p.page)).collect();
+                let mut chapter_stats = Stats::default();
+                for page in &pages {
+                    chapter_stats += page.stats;
+                }
+                stats += chapter_stats;
+                StatsItem::Chapter {
+                    title: chapter.title,
+                    slug: chapter.slug,
+                    stats: chapter_stats,
+                    pages,
+                }
+            }
+            TreeNode::Page(page) => {
+                let page = stats_page(page.page);
+                stats += page.stats;
+                StatsItem::Page(page)
+            }
+        })
+        .collect();
+
+    VolumeStats { stats, items }
+}
+
+fn stats_page(page: &PageData) -> PageStats<'_> {
+    let items = page
+        .chunks
+        .iter()
+        .map(|chunk| ChunkStats {
+            title: chunk.title.as_str(),
+            slug: chunk.slug.as_str(),
+            stats: chunk_stats(chunk),
+        })
+        .collect();
+
+    PageStats {
+        title: page.title.as_str(),
+        slug: page.slug.as_str(),
+        stats: page_stats(page),
+        items,
+    }
+}
@@ -0,0 +1,52 @@
+/// Levenshtein distance between two strings, counted in chars
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Leading sentences of a text that fit in `max_chars`, at least the first sentence, cut at
+/// a word boundary when it alone is too long
+pub(crate) fn lead_sentences(text: &str, max_chars: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let mut lead = "";
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let sentence_end = match chars.peek() {
+            None => true,
+            Some((_, next)) => matches!(c, '.' | '!' | '?') && *next == ' ',
+        };
+        if !sentence_end {
+            continue;
+        }
+
+        let candidate = &text[..index + c.len_utf8()];
+        if !lead.is_empty() && candidate.chars().count() > max_chars {
+            break;
+        }
+        lead = candidate;
+    }
+
+    if lead.chars().count() <= max_chars {
+        return lead.to_string();
+    }
+
+    // leaves room for the ellipsis
+    let cut: String = lead.chars().take(max_chars.saturating_sub(1)).collect();
+    match cut.rfind(' ') {
+        Some(space) => format!("{}…", cut[..space].trim_end_matches([',', ';', ':'])),
+        None => format!("{}…", cut),
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use super::{
+    frontmatter::Heading,
+    markdown::{page_slugger, transform_headings},
+    tree::{TreeNode, VolumeTree},
+    ChunkType, PageData,
+};
+
+/// Volume outline, so the frontend can render navigation without reading every page
+#[derive(Debug, Serialize)]
+pub struct Toc<'a> {
+    pub items: Vec<TocItem<'a>>,
+}
+
+/// Top-level outline entry, a chapter with its pages or a page without a chapter
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TocItem<'a> {
+    Chapter {
+        title: &'a str,
+        slug: &'a str,
+        pages: Vec<TocPage<'a>>,
+    },
+    Page(TocPage<'a>),
+}
+
+#[derive(Debug, Serialize)]
+pub struct TocPage<'a> {
+    pub title: &'a str,
+    pub slug: &'a str,
+    pub order: usize,
+    pub chunks: Vec<TocChunk<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TocChunk<'a> {
+    pub title: &'a str,
+    pub slug: &'a str,
+    #[serde(rename = "type")]
+    pub chunk_type: &'a ChunkType,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub headings: Vec<Heading>,
+}
+
+/// Builds the volume outline from the chapter tree
+pub fn build_toc(pages: &[PageData]) -> Toc<'_> {
+    let items = VolumeTree::build(pages)
+        .nodes
+        .iter()
+        .map(|node| match node {
+            TreeNode::Chapter(chapter) => TocItem::Chapter {
+                title: chapter.title,
+                slug: chapter.slug,
+                pages: chapter.pages.iter().map(|p| toc_page(p.page)).collect(),
+            },
+            TreeNode::Page(page) => TocItem::Page(toc_page(page.page)),
+        })
+        .collect();
+
+    Toc { items }
+}
+
+/// Serializes the volume outline to pretty-printed JSON
+pub fn serialize_toc(pages: &[PageData]) -> Result<String> {
+    serde_json::to_string_pretty(&build_toc(pages)).context("failed to serialize toc")
+}
+
+fn toc_page(page: &PageData) -> TocPage<'_> {
+    let mut slugger = page_slugger(page);
+    let chunks = page
+        .chunks
+        .iter()
+        .map(|chunk| TocChunk {
+            title: chunk.title.as_str(),
+            slug: chunk.slug.as_str(),
+            chunk_type: &chunk.chunk_type,
+            headings: transform_headings(&chunk.content, &mut slugger).1,
+        })
+        .collect();
+
+    TocPage {
+        title: page.title.as_str(),
+        slug: page.slug.as_str(),
+        order: page.order,
+        chunks,
+    }
+}
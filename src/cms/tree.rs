@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{validate::Diagnostic, PageData};
+
+/// Pages of a volume grouped under their chapters, in reading order
+#[derive(Debug)]
+pub struct VolumeTree<'a> {
+    pub nodes: Vec<TreeNode<'a>>,
+    /// all pages sorted by `order`, before grouping
+    sequence: Vec<&'a PageData>,
+}
+
+/// Top-level node, a chapter or a page without a chapter
+#[derive(Debug)]
+pub enum TreeNode<'a> {
+    Chapter(ChapterNode<'a>),
+    Page(PageNode<'a>),
+}
+
+#[derive(Debug)]
+pub struct ChapterNode<'a> {
+    pub title: &'a str,
+    pub slug: &'a str,
+    /// position among the chapters of the volume
+    pub order: usize,
+    /// slug of the previous chapter
+    pub prev: Option<&'a str>,
+    /// slug of the next chapter
+    pub next: Option<&'a str>,
+    pub pages: Vec<PageNode<'a>>,
+}
+
+#[derive(Debug)]
+pub struct PageNode<'a> {
+    pub page: &'a PageData,
+    /// slug of the previous page in reading order
+    pub prev: Option<&'a str>,
+    /// slug of the next page in reading order
+    pub next: Option<&'a str>,
+}
+
+impl<'a> ChapterNode<'a> {
+    pub fn first_page(&self) -> &'a str {
+        self.pages[0].page.slug.as_str()
+    }
+
+    pub fn last_page(&self) -> &'a str {
+        self.pages[self.pages.len() - 1].page.slug.as_str()
+    }
+}
+
+impl<'a> PageNode<'a> {
+    fn new(page: &'a PageData) -> Self {
+        Self {
+            page,
+            prev: None,
+            next: None,
+        }
+    }
+}
+
+impl<'a> VolumeTree<'a> {
+    /// Groups pages by their parent chapter. A chapter appears where its first page is, pages
+    /// of the chapter that come later in `order` are moved under it. `validate` reports that
+    /// as an error, so a volume is never written out of reading order.
+    pub fn build(pages: &'a [PageData]) -> Self {
+        let mut sequence: Vec<&PageData> = pages.iter().collect();
+        sequence.sort_by_key(|page| page.order);
+
+        let mut nodes = Vec::<TreeNode>::new();
+        let mut chapter_index = HashMap::<&str, usize>::new();
+
+        for page in &sequence {
+            let Some(parent) = &page.parent else {
+                nodes.push(TreeNode::Page(PageNode::new(page)));
+                continue;
+            };
+
+            match chapter_index.get(parent.slug.as_str()) {
+                Some(&index) => {
+                    if let TreeNode::Chapter(chapter) = &mut nodes[index] {
+                        chapter.pages.push(PageNode::new(page));
+                    }
+                }
+                None => {
+                    chapter_index.insert(parent.slug.as_str(), nodes.len());
+                    nodes.push(TreeNode::Chapter(ChapterNode {
+                        title: parent.title.as_str(),
+                        slug: parent.slug.as_str(),
+                        order: chapter_index.len() - 1,
+                        prev: None,
+                        next: None,
+                        pages: vec![PageNode::new(page)],
+                    }));
+                }
+            }
+        }
+
+        let mut tree = Self { nodes, sequence };
+        tree.link();
+        tree
+    }
+
+    /// Pages in reading order
+    pub fn pages(&self) -> impl Iterator<Item = &PageNode<'a>> {
+        self.nodes.iter().flat_map(|node| match node {
+            TreeNode::Chapter(chapter) => chapter.pages.iter(),
+            TreeNode::Page(page) => std::slice::from_ref(page).iter(),
+        })
+    }
+
+    pub fn chapters(&self) -> impl Iterator<Item = &ChapterNode<'a>> {
+        self.nodes.iter().filter_map(|node| match node {
+            TreeNode::Chapter(chapter) => Some(chapter),
+            TreeNode::Page(_) => None,
+        })
+    }
+
+    /// Checks that chapters are contiguous and consistently named, and that page order is unambiguous
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        let mut current: Option<&str> = None;
+        let mut seen = HashMap::<&str, &str>::new();
+        for page in &self.sequence {
+            let parent = page.parent.as_ref();
+            let slug = parent.map(|p| p.slug.as_str());
+
+            if let Some(parent) = parent {
+                match seen.get(parent.slug.as_str()) {
+                    Some(title) if *title != parent.title => {
+                        diagnostics.push(Diagnostic::error(
+                            Some(&page.slug),
+                            None,
+                            format!(
+                                "Chapter '{}' is titled both '{}' and '{}'",
+                                parent.slug, title, parent.title
+                            ),
+                        ));
+                    }
+                    Some(_) if current != slug => {
+                        diagnostics.push(Diagnostic::error(
+                            Some(&page.slug),
+                            None,
+                            format!(
+                                "Chapter '{}' is not contiguous, page '{}' (order {}) comes after pages outside the chapter, so it would be moved out of reading order",
+                                parent.slug, page.slug, page.order
+                            ),
+                        ));
+                    }
+                    Some(_) => {}
+                    None => {
+                        seen.insert(parent.slug.as_str(), parent.title.as_str());
+                    }
+                }
+            }
+
+            current = slug;
+        }
+
+        let mut orders = BTreeMap::<usize, Vec<&str>>::new();
+        for page in &self.sequence {
+            orders
+                .entry(page.order)
+                .or_default()
+                .push(page.slug.as_str());
+        }
+        for (order, slugs) in orders.iter().filter(|(_, slugs)| slugs.len() > 1) {
+            diagnostics.push(Diagnostic::warning(
+                None,
+                None,
+                format!(
+                    "Pages '{}' share order {}, their relative order is ambiguous",
+                    slugs.join("', '"),
+                    order
+                ),
+            ));
+        }
+
+        diagnostics
+    }
+
+    /// Fills in prev/next links for pages and chapters
+    fn link(&mut self) {
+        let slugs: Vec<&'a str> = self.pages().map(|p| p.page.slug.as_str()).collect();
+        let chapter_slugs: Vec<&'a str> = self.chapters().map(|c| c.slug).collect();
+
+        let mut page_index: usize = 0;
+        let mut link_page = |node: &mut PageNode<'a>| {
+            node.prev = page_index.checked_sub(1).map(|i| slugs[i]);
+            node.next = slugs.get(page_index + 1).copied();
+            page_index += 1;
+        };
+
+        for node in self.nodes.iter_mut() {
+            match node {
+                TreeNode::Chapter(chapter) => {
+                    chapter.prev = chapter.order.checked_sub(1).map(|i| chapter_slugs[i]);
+                    chapter.next = chapter_slugs.get(chapter.order + 1).copied();
+                    chapter.pages.iter_mut().for_each(&mut link_page);
+                }
+                TreeNode::Page(page) => link_page(page),
+            }
+        }
+    }
+}
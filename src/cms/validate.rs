@@ -0,0 +1,275 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::Serialize;
+
+use super::{
+    access::invalid_free_pages,
+    chunk::RevealMode,
+    markdown::{flatten_headings, page_slugger, transform_headings},
+    page::PageData,
+};
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found in volume content, located by page and chunk where possible
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub page_slug: Option<String>,
+    pub chunk_slug: Option<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(page_slug: Option<&str>, chunk_slug: Option<&str>, message: String) -> Self {
+        Self::new(Severity::Error, page_slug, chunk_slug, message)
+    }
+
+    pub fn warning(page_slug: Option<&str>, chunk_slug: Option<&str>, message: String) -> Self {
+        Self::new(Severity::Warning, page_slug, chunk_slug, message)
+    }
+
+    fn new(
+        severity: Severity,
+        page_slug: Option<&str>,
+        chunk_slug: Option<&str>,
+        message: String,
+    ) -> Self {
+        Self {
+            severity,
+            page_slug: page_slug.map(str::to_string),
+            chunk_slug: chunk_slug.map(str::to_string),
+            message,
+        }
+    }
+}
+
+/// Returns true if any diagnostic is an error
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+/// Checks that page and chunk slugs are unique and usable in URLs and anchors
+pub fn validate_slugs(pages: &[PageData]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    // page slugs become file names and URL paths
+    let mut page_slugs = BTreeMap::<&str, Vec<&str>>::new();
+    for page in pages {
+        page_slugs
+            .entry(page.slug.as_str())
+            .or_default()
+            .push(page.title.as_str());
+
+        if let Some(message) = check_slug_characters(&page.slug) {
+            diagnostics.push(Diagnostic::warning(
+                Some(&page.slug),
+                None,
+                format!("Page slug {}", message),
+            ));
+        }
+    }
+
+    for (slug, titles) in page_slugs.iter().filter(|(_, titles)| titles.len() > 1) {
+        diagnostics.push(Diagnostic::error(
+            Some(slug),
+            None,
+            format!(
+                "Page slug '{}' is used by {} pages: '{}'",
+                slug,
+                titles.len(),
+                titles.join("', '")
+            ),
+        ));
+    }
+
+    // chunk slugs are looked up by CRI and used as anchors, so they must be unique in the volume
+    let mut chunk_slugs = BTreeMap::<&str, Vec<&str>>::new();
+    for page in pages {
+        for chunk in &page.chunks {
+            chunk_slugs
+                .entry(chunk.slug.as_str())
+                .or_default()
+                .push(page.slug.as_str());
+
+            // existing slugs key stored CRI responses and embeddings, so they can't just be renamed
+            if let Some(message) = check_slug_characters(&chunk.slug) {
+                diagnostics.push(Diagnostic::warning(
+                    Some(&page.slug),
+                    Some(&chunk.slug),
+                    format!("Chunk slug {}", message),
+                ));
+            }
+        }
+    }
+
+    for (slug, page_slugs) in chunk_slugs.iter().filter(|(_, pages)| pages.len() > 1) {
+        diagnostics.push(Diagnostic::error(
+            None,
+            Some(slug),
+            format!(
+                "Chunk slug '{}' is used {} times, in pages '{}'",
+                slug,
+                page_slugs.len(),
+                page_slugs.join("', '")
+            ),
+        ));
+    }
+
+    // constructed response ids identify stored responses, so they must be unique as well
+    let mut cri_ids = BTreeMap::<&str, Vec<&str>>::new();
+    for chunk in pages.iter().flat_map(|page| &page.chunks) {
+        for item in &chunk.cri {
+            cri_ids
+                .entry(item.id.as_str())
+                .or_default()
+                .push(chunk.slug.as_str());
+        }
+    }
+
+    for (id, chunk_slugs) in cri_ids.iter().filter(|(_, chunks)| chunks.len() > 1) {
+        diagnostics.push(Diagnostic::error(
+            None,
+            chunk_slugs.first().copied(),
+            format!(
+                "Constructed response id '{}' is used {} times, in chunks '{}'",
+                id,
+                chunk_slugs.len(),
+                chunk_slugs.join("', '")
+            ),
+        ));
+    }
+
+    for page in pages {
+        diagnostics.extend(validate_anchor_ids(page));
+    }
+
+    diagnostics
+}
+
+/// Checks that every `FreePages` entry of the volume resolves to at least one page
+pub fn validate_free_pages(entries: &[String], pages: &[PageData]) -> Vec<Diagnostic> {
+    invalid_free_pages(entries, pages)
+        .into_iter()
+        .map(|(entry, reason)| {
+            Diagnostic::error(
+                None,
+                None,
+                format!("Free page entry '{}' {}", entry, reason),
+            )
+        })
+        .collect()
+}
+
+/// Checks that every auxiliary chunk has a previous chunk to be revealed with
+pub fn validate_reveal_modes(pages: &[PageData]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for page in pages {
+        let mut previous: Option<RevealMode> = None;
+        for chunk in &page.chunks {
+            let (page_slug, chunk_slug) = (Some(page.slug.as_str()), Some(chunk.slug.as_str()));
+            if chunk.reveal == RevealMode::Auxiliary {
+                match previous {
+                    None => diagnostics.push(Diagnostic::error(
+                        page_slug,
+                        chunk_slug,
+                        "Auxiliary chunk is the first of its page, there is no previous chunk to reveal it with"
+                            .to_string(),
+                    )),
+                    Some(RevealMode::AlwaysVisible) => diagnostics.push(Diagnostic::warning(
+                        page_slug,
+                        chunk_slug,
+                        "Auxiliary chunk follows an always visible chunk, so it is never blurred"
+                            .to_string(),
+                    )),
+                    _ => {}
+                }
+
+                // the chunk is never read on its own, so its questions are never asked
+                if !chunk.cri.is_empty() {
+                    diagnostics.push(Diagnostic::warning(
+                        page_slug,
+                        chunk_slug,
+                        "Auxiliary chunk has constructed response items, which are skipped"
+                            .to_string(),
+                    ));
+                }
+            }
+            previous = Some(chunk.reveal);
+        }
+    }
+
+    diagnostics
+}
+
+/// Checks that heading ids don't collide with chunk anchors or each other on the same page.
+/// Generated ids are suffixed by the page slugger, so only explicit `{#id}`s can collide.
+fn validate_anchor_ids(page: &PageData) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let chunk_slugs: HashSet<&str> = page.chunks.iter().map(|c| c.slug.as_str()).collect();
+    let mut heading_ids = HashMap::<String, &str>::new();
+    let mut slugger = page_slugger(page);
+
+    for chunk in &page.chunks {
+        let (_, headings) = transform_headings(&chunk.content, &mut slugger);
+
+        for heading in flatten_headings(&headings) {
+            if chunk_slugs.contains(heading.slug.as_str()) {
+                diagnostics.push(Diagnostic::error(
+                    Some(&page.slug),
+                    Some(&chunk.slug),
+                    format!(
+                        "Heading '{}' has id '{}', which collides with a chunk slug",
+                        heading.title, heading.slug
+                    ),
+                ));
+            } else if let Some(other) = heading_ids.get(&heading.slug) {
+                diagnostics.push(Diagnostic::error(
+                    Some(&page.slug),
+                    Some(&chunk.slug),
+                    format!(
+                        "Heading '{}' has id '{}', which is also used by a heading in chunk '{}'",
+                        heading.title, heading.slug, other
+                    ),
+                ));
+            } else {
+                heading_ids.insert(heading.slug.clone(), chunk.slug.as_str());
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Describes the problem if a slug is empty or has characters outside the URL-safe set
+fn check_slug_characters(slug: &str) -> Option<String> {
+    if slug.trim().is_empty() {
+        return Some("is empty".to_string());
+    }
+
+    let mut invalid: Vec<char> = slug
+        .chars()
+        .filter(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~')))
+        .collect();
+    if invalid.is_empty() {
+        return None;
+    }
+
+    invalid.sort_unstable();
+    invalid.dedup();
+    Some(format!(
+        "'{}' contains characters that are not URL-safe: {}",
+        slug,
+        invalid
+            .iter()
+            .map(|c| format!("'{}'", c))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
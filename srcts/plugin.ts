@@ -20,6 +20,8 @@ export const rehypeWrapHeadingSection = () => {
 
 				const id = node.properties.id;
 				const className = node.properties.className;
+				// the reveal mode belongs to the whole chunk, not its heading
+				const { dataReveal, ...headingProperties } = node.properties;
 
 				currentSection = h(
 					"section",
@@ -27,10 +29,11 @@ export const rehypeWrapHeadingSection = () => {
 						class: "content-chunk",
 						"data-chunk-slug": id,
 						"aria-labelledby": id,
+						"data-reveal": dataReveal ?? "standalone",
 					},
 					[
 						{ type: "text", value: "\n\n" },
-						h("h2", node.properties, [...node.children]),
+						h("h2", headingProperties, [...node.children]),
 						{ type: "text", value: "\n" },
 					],
 				);
@@ -38,6 +41,21 @@ export const rehypeWrapHeadingSection = () => {
 				return [SKIP, index + 1];
 			}
 
+			// the cloze test follows the chunks, it must not be revealed with the last one
+			if (
+				node.type === "element" &&
+				node.tagName === "i-cloze-test" &&
+				parent === tree &&
+				index !== undefined
+			) {
+				if (currentSection) {
+					sections.push(currentSection);
+					currentSection = null;
+				}
+				sections.push(node);
+				return [SKIP, index + 1];
+			}
+
 			if (currentSection && parent === tree && index !== undefined) {
 				currentSection.children.push(node);
 				return [SKIP, index + 1];
@@ -69,12 +87,14 @@ export const rehypeFrontmatter = () => {
 		if (match) {
 			const [, frontmatterString] = match;
 			const frontmatter = yaml.parse(frontmatterString);
-			const cri = Object.fromEntries(
-				frontmatter.cri.map((item) => [
-					item.slug,
-					{ question: item.question, answer: item.answer },
-				]),
-			);
+			// a chunk can have several items, keep all of them in order
+			const cri: Record<string, { question: string; answer: string }[]> = {};
+			for (const item of frontmatter.cri) {
+				(cri[item.slug] ??= []).push({
+					question: item.question,
+					answer: item.answer,
+				});
+			}
 			file.cri = cri;
 		}
 		visit(tree, "element", () => {
@@ -87,7 +107,7 @@ export const rehypeAddCri = () => {
 	return (tree: Root, file) => {
 		const cri = file.cri as Record<
 			string,
-			{ question: string; answer: string }
+			{ question: string; answer: string }[]
 		>;
 		visit(tree, "element", (node) => {
 			if (node.tagName === "section") {
@@ -99,9 +119,7 @@ export const rehypeAddCri = () => {
 			) {
 				const labelId = node.properties.ariaLabelledBy as string;
 
-				if (cri[labelId]) {
-					const { question, answer } = cri[labelId];
-
+				for (const { question, answer } of cri[labelId] ?? []) {
 					const newElement: Element = {
 						type: "element",
 						tagName: "i-question",